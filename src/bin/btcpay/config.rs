@@ -0,0 +1,179 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use btcpay::*;
+
+/// Profiles stored in `~/.config/btcpay/config.toml`:
+///
+/// ```toml
+/// default_profile = "shop"
+///
+/// [profiles.shop]
+/// url = "https://btcpay.example.com"
+/// key = "<hex private key>"
+/// merchant = "<merchant token>"
+/// network = "bitcoin"
+/// ```
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Config {
+    pub default_profile: Option<String>,
+    #[serde(default)]
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct Profile {
+    pub url: Option<String>,
+    pub key: Option<String>,
+    pub merchant: Option<String>,
+    pub network: Option<String>,
+}
+
+impl Config {
+    pub fn default_path() -> Result<PathBuf, Box<dyn Error>> {
+        let dir = dirs::config_dir().ok_or("can't find the configuration directory")?;
+
+        Ok(dir.join("btcpay").join("config.toml"))
+    }
+
+    /// Loads the configuration, or an empty one if the file doesn't exist yet
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        match fs::read_to_string(path) {
+            Ok(content) => Ok(toml::from_str(&content)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Config::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Writes the configuration to a temporary file readable only by the owner, since it holds
+    /// private keys, and moves it into place
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let temp = path.with_extension("toml.tmp");
+        match fs::remove_file(&temp) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+            _ => {}
+        }
+
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let mut file = options.open(&temp)?;
+        file.write_all(toml::to_string_pretty(self)?.as_bytes())?;
+        file.sync_all()?;
+        fs::rename(&temp, path)?;
+
+        Ok(())
+    }
+
+    /// Name of the profile selected on the command line, falling back to the default one
+    pub fn profile_name(&self, selected: Option<&str>) -> String {
+        selected
+            .or(self.default_profile.as_deref())
+            .unwrap_or("default")
+            .to_string()
+    }
+}
+
+impl Profile {
+    pub fn keypair(&self) -> Result<KeyPair, Box<dyn Error>> {
+        let key = self
+            .key
+            .as_ref()
+            .ok_or("no key in the profile, run `btcpay keygen --save` first")?;
+
+        Ok(SecretKey::from_slice(&Vec::<u8>::from_hex(key)?)?.into())
+    }
+
+    pub fn client(&self) -> Result<BTCPayClient, Box<dyn Error>> {
+        let url = self.url.as_ref().ok_or("no url in the profile")?;
+
+        let mut builder = BTCPayClient::builder(url, self.keypair()?);
+        if let Some(merchant) = &self.merchant {
+            builder = builder.merchant(merchant);
+        }
+        if let Some(network) = &self.network {
+            builder = builder.network(network.parse()?);
+        }
+
+        Ok(builder.build()?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const CONFIG: &str = r#"
+default_profile = "shop"
+
+[profiles.shop]
+url = "https://btcpay.example.com"
+key = "0101010101010101010101010101010101010101010101010101010101010101"
+merchant = "merchant"
+network = "testnet"
+
+[profiles.empty]
+"#;
+
+    #[test]
+    fn test_parse_config() {
+        let config: Config = toml::from_str(CONFIG).unwrap();
+        assert_eq!(config.profile_name(None), "shop");
+        assert_eq!(config.profile_name(Some("empty")), "empty");
+        assert_eq!(Config::default().profile_name(None), "default");
+
+        let shop = &config.profiles["shop"];
+        assert_eq!(shop.merchant.as_deref(), Some("merchant"));
+        assert_eq!(
+            shop.client().unwrap().network(),
+            Some(btcpay::Network::Testnet)
+        );
+
+        let empty = &config.profiles["empty"];
+        assert!(empty.keypair().is_err());
+        assert!(empty.client().is_err());
+    }
+
+    #[test]
+    fn test_load_and_save() {
+        let dir = std::env::temp_dir().join(format!("btcpay-config-{}", std::process::id()));
+        let path = dir.join("config.toml");
+        assert!(Config::load(&path).unwrap().profiles.is_empty());
+
+        // A file left readable by others is replaced rather than rewritten
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(&path, "").unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+        }
+
+        let config: Config = toml::from_str(CONFIG).unwrap();
+        config.save(&path).unwrap();
+        assert!(!path.with_extension("toml.tmp").exists());
+        let loaded = Config::load(&path).unwrap();
+        assert_eq!(loaded.default_profile.as_deref(), Some("shop"));
+        assert_eq!(loaded.profiles.len(), 2);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
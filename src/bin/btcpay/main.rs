@@ -0,0 +1,289 @@
+use std::error::Error;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use clap::{Parser, Subcommand};
+
+use btcpay::core::cryptography::Cryptography;
+use btcpay::*;
+
+mod config;
+mod output;
+
+use config::Config;
+use output::*;
+
+/// Command-line client for BTCPay Server
+#[derive(Debug, Parser)]
+#[command(name = "btcpay", version)]
+struct Cli {
+    /// Configuration file, `~/.config/btcpay/config.toml` by default
+    #[arg(long, global = true, env = "BTCPAY_CONFIG")]
+    config: Option<PathBuf>,
+    /// Profile to use, the configured default one if omitted
+    #[arg(long, short, global = true, env = "BTCPAY_PROFILE")]
+    profile: Option<String>,
+    /// Print JSON instead of tables
+    #[arg(long, global = true)]
+    json: bool,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Generate a new client key
+    Keygen {
+        /// Store the key in the profile
+        #[arg(long)]
+        save: bool,
+    },
+    /// Pair the profile key with a store and save the merchant token
+    Pair {
+        /// Pairing code created in the store settings
+        code: String,
+        /// Server URL, saved in the profile
+        #[arg(long)]
+        url: Option<String>,
+    },
+    #[command(subcommand)]
+    Invoice(InvoiceCommand),
+    /// Show the store rates
+    Rates {
+        /// Currency pairs, like BTC_USD
+        #[arg(default_value = "BTC_USD")]
+        pairs: Vec<String>,
+    },
+}
+
+/// Create, inspect and follow invoices
+#[derive(Debug, Subcommand)]
+enum InvoiceCommand {
+    Create {
+        price: f32,
+        currency: String,
+        #[arg(long)]
+        order_id: Option<String>,
+        #[arg(long)]
+        item_desc: Option<String>,
+        #[arg(long)]
+        buyer_email: Option<String>,
+        #[arg(long)]
+        notification_url: Option<String>,
+    },
+    Get {
+        id: String,
+    },
+    List {
+        #[arg(long)]
+        status: Option<InvoiceStatus>,
+        #[arg(long)]
+        order_id: Option<String>,
+        #[arg(long, default_value_t = 20)]
+        limit: usize,
+        #[arg(long)]
+        offset: Option<usize>,
+    },
+    /// Poll an invoice and print its status changes until it settles
+    Watch {
+        id: String,
+        /// Seconds between polls
+        #[arg(long, default_value_t = 10)]
+        interval: u64,
+    },
+}
+
+#[tokio::main]
+async fn main() {
+    if let Err(e) = run(Cli::parse()).await {
+        eprintln!("error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+async fn run(cli: Cli) -> Result<(), Box<dyn Error>> {
+    let config_path = match cli.config {
+        Some(path) => path,
+        None => Config::default_path()?,
+    };
+    let mut config = Config::load(&config_path)?;
+    let profile_name = config.profile_name(cli.profile.as_deref());
+    let profile = config
+        .profiles
+        .get(&profile_name)
+        .cloned()
+        .unwrap_or_default();
+
+    match cli.command {
+        Command::Keygen { save } => {
+            let keypair = Cryptography::generate_keypair();
+            let key = keypair.secret().display_secret().to_string();
+            let sin = Cryptography::get_sin_from_key(&keypair.public);
+
+            if save {
+                if profile.key.is_some() {
+                    return Err(format!("profile `{}` already has a key", profile_name).into());
+                }
+                config.profiles.entry(profile_name.clone()).or_default().key = Some(key.clone());
+                config.save(&config_path)?;
+            }
+
+            if cli.json {
+                print_json(&serde_json::json!({
+                    "key": key,
+                    "publicKey": keypair.public.to_string(),
+                    "sin": sin,
+                }))?;
+            } else {
+                print_table(
+                    &["FIELD", "VALUE"],
+                    &[
+                        vec!["Key".into(), key],
+                        vec!["Public key".into(), keypair.public.to_string()],
+                        vec!["SIN".into(), sin],
+                    ],
+                );
+            }
+        }
+        Command::Pair { code, url } => {
+            let mut profile = profile;
+            if url.is_some() {
+                profile.url = url;
+            }
+            let response = profile.client()?.pair_client(&code).await?;
+
+            profile.merchant = Some(response.merchant.clone());
+            config.profiles.insert(profile_name.clone(), profile);
+            config.save(&config_path)?;
+
+            if cli.json {
+                print_json(&serde_json::json!({ "merchant": response.merchant }))?;
+            } else {
+                println!("Paired profile `{}`", profile_name);
+            }
+        }
+        Command::Invoice(command) => run_invoice(command, &profile.client()?, cli.json).await?,
+        Command::Rates { pairs } => {
+            let pairs: Vec<&str> = pairs.iter().map(String::as_str).collect();
+            let rates = profile.client()?.get_rates(&pairs).await?;
+
+            if cli.json {
+                print_json(&rates)?;
+            } else {
+                let rows: Vec<_> = rates
+                    .iter()
+                    .map(|r| vec![r.currency_pair.clone(), r.rate.to_string(), r.name.clone()])
+                    .collect();
+                print_table(&["PAIR", "RATE", "NAME"], &rows);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+async fn run_invoice(
+    command: InvoiceCommand,
+    client: &BTCPayClient,
+    json: bool,
+) -> Result<(), Box<dyn Error>> {
+    match command {
+        InvoiceCommand::Create {
+            price,
+            currency,
+            order_id,
+            item_desc,
+            buyer_email,
+            notification_url,
+        } => {
+            let args = CreateInvoiceArgs {
+                order_id: order_id.map(OrderId::String),
+                item_desc,
+                buyer_email,
+                notification_url,
+                ..CreateInvoiceArgs::new(&currency, price)
+            };
+            let invoice = client.create_invoice(args).await?;
+
+            if json {
+                print_json(&invoice)?;
+            } else {
+                print_invoice(&invoice);
+            }
+        }
+        InvoiceCommand::Get { id } => {
+            let invoice = client.get_invoice(&id).await?;
+
+            if json {
+                print_json(&invoice)?;
+            } else {
+                print_invoice(&invoice);
+            }
+        }
+        InvoiceCommand::List {
+            status,
+            order_id,
+            limit,
+            offset,
+        } => {
+            let args = GetInvoicesArgs {
+                status: status.into_iter().collect(),
+                order_id,
+                limit: Some(limit),
+                offset,
+                ..Default::default()
+            };
+            let invoices = client.get_invoices(args).await?;
+
+            if json {
+                print_json(&invoices)?;
+            } else {
+                let rows: Vec<_> = invoices.iter().map(invoice_row).collect();
+                print_table(INVOICE_HEADERS, &rows);
+            }
+        }
+        InvoiceCommand::Watch { id, interval } => {
+            let mut invoice = client.get_invoice(&id).await?;
+            let mut lifecycle = InvoiceLifecycle::from_invoice(&invoice).allow_skips(true);
+            print_watched(&invoice, json)?;
+
+            while !lifecycle.is_terminal() {
+                tokio::time::sleep(Duration::from_secs(interval)).await;
+                invoice = client.get_invoice(&id).await?;
+
+                let previous = lifecycle.clone();
+                match lifecycle.observe_invoice(&invoice) {
+                    Ok(_) if lifecycle == previous => continue,
+                    Ok(_) => {}
+                    Err(e) => {
+                        eprintln!("warning: {}", e);
+                        lifecycle = InvoiceLifecycle::from_invoice(&invoice).allow_skips(true);
+                    }
+                }
+                print_watched(&invoice, json)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Prints one line per observed status of `invoice watch`
+fn print_watched(invoice: &Invoice, json: bool) -> Result<(), Box<dyn Error>> {
+    if json {
+        println!(
+            "{}",
+            serde_json::json!({
+                "id": invoice.id,
+                "status": invoice.status,
+                "exceptionStatus": invoice.exception_status,
+            })
+        );
+    } else {
+        let row = invoice_row(invoice);
+        println!("{} {} {}", row[0], row[1], row[2]);
+    }
+
+    Ok(())
+}
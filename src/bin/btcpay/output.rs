@@ -0,0 +1,129 @@
+use serde::Serialize;
+
+use btcpay::models::timestamp;
+use btcpay::*;
+
+/// Formats rows as left-aligned columns, padded to the widest cell
+fn format_table(headers: &[&str], rows: &[Vec<String>]) -> String {
+    let mut widths: Vec<usize> = headers.iter().map(|h| h.len()).collect();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let format_row = |cells: Vec<&str>| {
+        let line = cells
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect::<Vec<_>>()
+            .join("  ");
+        format!("{}\n", line.trim_end())
+    };
+
+    let mut table = format_row(headers.to_vec());
+    for row in rows {
+        table.push_str(&format_row(row.iter().map(String::as_str).collect()));
+    }
+
+    table
+}
+
+pub fn print_table(headers: &[&str], rows: &[Vec<String>]) {
+    print!("{}", format_table(headers, rows));
+}
+
+pub fn print_json<T: Serialize>(value: &T) -> Result<(), serde_json::Error> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
+
+fn exception(status: &ExceptionStatus) -> String {
+    match status {
+        ExceptionStatus::None => "-".to_string(),
+        other => other.to_string(),
+    }
+}
+
+pub fn invoice_row(invoice: &Invoice) -> Vec<String> {
+    vec![
+        invoice.id.clone(),
+        invoice.status.to_string(),
+        exception(&invoice.exception_status),
+        format!("{} {}", invoice.price, invoice.currency),
+        invoice.order_id.clone().unwrap_or_else(|| "-".to_string()),
+        timestamp::to_rfc3339(&invoice.invoice_time),
+    ]
+}
+
+pub const INVOICE_HEADERS: &[&str] = &["ID", "STATUS", "EXCEPTION", "PRICE", "ORDER ID", "CREATED"];
+
+pub fn print_invoice(invoice: &Invoice) {
+    let mut rows = vec![
+        vec!["ID".to_string(), invoice.id.clone()],
+        vec!["Status".to_string(), invoice.status.to_string()],
+        vec![
+            "Exception".to_string(),
+            exception(&invoice.exception_status),
+        ],
+        vec![
+            "Price".to_string(),
+            format!("{} {}", invoice.price, invoice.currency),
+        ],
+        vec![
+            "Order ID".to_string(),
+            invoice.order_id.clone().unwrap_or_else(|| "-".to_string()),
+        ],
+        vec![
+            "Expires".to_string(),
+            timestamp::to_rfc3339(&invoice.expiration_time),
+        ],
+        vec!["Checkout".to_string(), invoice.url.clone()],
+    ];
+
+    let mut methods: Vec<_> = invoice.crypto_info.iter().collect();
+    methods.sort_by_key(|info| info.payment_method_id());
+    for info in methods {
+        let id = info.payment_method_id();
+        rows.push(vec![
+            format!("{} due", id),
+            format!("{} (paid {})", info.due, info.crypto_paid),
+        ]);
+        if let Some(address) = &info.address {
+            rows.push(vec![format!("{} address", id), address.clone()]);
+        }
+    }
+
+    print_table(&["FIELD", "VALUE"], &rows);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_format_table() {
+        let rows = vec![
+            vec!["a".to_string(), "new".to_string(), "-".to_string()],
+            vec!["bcdef".to_string(), "expired".to_string(), String::new()],
+        ];
+
+        assert_eq!(
+            format_table(&["ID", "STATUS", "EXCEPTION"], &rows),
+            "ID     STATUS   EXCEPTION\n\
+             a      new      -\n\
+             bcdef  expired\n"
+        );
+    }
+
+    #[test]
+    fn test_exception() {
+        assert_eq!(exception(&ExceptionStatus::None), "-");
+        assert_eq!(exception(&ExceptionStatus::PaidPartial), "paidPartial");
+        assert_eq!(
+            exception(&ExceptionStatus::Unknown("refunded".into())),
+            "refunded"
+        );
+    }
+}
@@ -0,0 +1,304 @@
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+use bitcoin::Network;
+use regex::Regex;
+use reqwest::{header, Certificate, Client, Proxy};
+
+use super::client::{BTCPayClient, Error};
+use super::cryptography::Cryptography;
+use super::retry::RetryPolicy;
+use super::signer::Signer;
+
+/// Configures the HTTP transport of a [`BTCPayClient`].
+///
+/// Timeouts, proxies, root certificates and the user agent only apply to the `reqwest::Client`
+/// built by [`build`](BTCPayClientBuilder::build): they are ignored when a preconfigured client is
+/// passed with [`client`](BTCPayClientBuilder::client). Default headers are sent in both cases.
+#[derive(Debug)]
+pub struct BTCPayClientBuilder {
+    host: String,
+    signer: Arc<dyn Signer>,
+    token: Option<String>,
+    api_key: Option<String>,
+    retry_policy: RetryPolicy,
+    network: Option<Network>,
+    connect_timeout: Option<Duration>,
+    timeout: Option<Duration>,
+    proxies: Vec<Proxy>,
+    root_certificates: Vec<Certificate>,
+    user_agent: String,
+    headers: header::HeaderMap,
+    client: Option<Client>,
+}
+
+impl BTCPayClientBuilder {
+    pub fn new<S: Signer + 'static>(host: &str, signer: S) -> Self {
+        BTCPayClientBuilder {
+            host: host.to_string(),
+            signer: Arc::new(signer),
+            token: None,
+            api_key: None,
+            retry_policy: RetryPolicy::default(),
+            network: None,
+            connect_timeout: None,
+            timeout: None,
+            proxies: Vec::new(),
+            root_certificates: Vec::new(),
+            user_agent: concat!("rust-btcpay/", env!("CARGO_PKG_VERSION")).to_string(),
+            headers: header::HeaderMap::new(),
+            client: None,
+        }
+    }
+
+    pub fn merchant(mut self, token: &str) -> Self {
+        self.token = Some(token.to_string());
+        self
+    }
+
+    /// Greenfield API key, required by the `/api/v1` endpoints
+    pub fn api_key(mut self, api_key: &str) -> Self {
+        self.api_key = Some(api_key.to_string());
+        self
+    }
+
+    pub fn retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Network the server is expected to run on. If not set, it's detected from the addresses of
+    /// the first invoice received: the Greenfield server info doesn't report the network, so it
+    /// can't be detected up front.
+    pub fn network(mut self, network: Network) -> Self {
+        self.network = Some(network);
+        self
+    }
+
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Timeout for the whole request, from connecting until the response body has been read
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Routes requests through `proxy`, which may be an HTTP(S) or a `socks5h://` proxy (the
+    /// latter is required to reach onion services)
+    pub fn proxy(mut self, proxy: Proxy) -> Self {
+        self.proxies.push(proxy);
+        self
+    }
+
+    pub fn add_root_certificate(mut self, certificate: Certificate) -> Self {
+        self.root_certificates.push(certificate);
+        self
+    }
+
+    pub fn user_agent(mut self, user_agent: &str) -> Self {
+        self.user_agent = user_agent.to_string();
+        self
+    }
+
+    pub fn default_header(mut self, name: header::HeaderName, value: header::HeaderValue) -> Self {
+        self.headers.insert(name, value);
+        self
+    }
+
+    pub fn default_headers(mut self, headers: header::HeaderMap) -> Self {
+        self.headers.extend(headers);
+        self
+    }
+
+    pub fn client(mut self, client: Client) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    /// Fails with [`Error::InvalidHost`] unless the host is an `http` or `https` URL
+    pub fn build(self) -> Result<BTCPayClient, Error> {
+        let host = Regex::new(r"/+$")
+            .unwrap()
+            .replace(&self.host, "")
+            .to_string();
+        match reqwest::Url::parse(&host) {
+            Ok(url) if matches!(url.scheme(), "http" | "https") && url.has_host() => {}
+            _ => return Err(Error::InvalidHost(self.host)),
+        }
+
+        let mut headers = header::HeaderMap::new();
+        headers.insert(
+            header::CONTENT_TYPE,
+            header::HeaderValue::from_static("application/json"),
+        );
+        headers.insert(
+            header::ACCEPT,
+            header::HeaderValue::from_static("application/json"),
+        );
+        headers.insert(
+            "X-Accept-Version",
+            header::HeaderValue::from_static("2.0.0"),
+        );
+        headers.extend(self.headers);
+
+        let client = match self.client {
+            Some(client) => client,
+            None => {
+                let mut builder = Client::builder().user_agent(self.user_agent);
+                if let Some(timeout) = self.connect_timeout {
+                    builder = builder.connect_timeout(timeout);
+                }
+                if let Some(timeout) = self.timeout {
+                    builder = builder.timeout(timeout);
+                }
+                for proxy in self.proxies {
+                    builder = builder.proxy(proxy);
+                }
+                for certificate in self.root_certificates {
+                    builder = builder.add_root_certificate(certificate);
+                }
+
+                builder.build()?
+            }
+        };
+
+        Ok(BTCPayClient {
+            host,
+            client_id: Cryptography::get_sin_from_key(&self.signer.public_key()),
+            token: self.token,
+            api_key: self.api_key,
+            signer: self.signer,
+            client,
+            headers,
+            retry_policy: self.retry_policy,
+            network: Arc::new(self.network.map(OnceLock::from).unwrap_or_default()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use mockito::{Matcher, Server};
+
+    use super::*;
+
+    fn builder(host: &str) -> BTCPayClientBuilder {
+        BTCPayClientBuilder::new(host, Cryptography::generate_keypair())
+    }
+
+    #[test]
+    fn test_defaults() {
+        let client = builder("https://btcpay.example.com//").build().unwrap();
+
+        assert_eq!(client.host, "https://btcpay.example.com");
+        assert_eq!(client.headers[header::ACCEPT], "application/json");
+        assert_eq!(client.headers[header::CONTENT_TYPE], "application/json");
+        assert_eq!(client.headers["X-Accept-Version"], "2.0.0");
+        assert!(client.token.is_none());
+        assert!(client.api_key.is_none());
+        assert!(client.network().is_none());
+        assert_eq!(client.retry_policy.max_attempts, 3);
+    }
+
+    #[test]
+    fn test_options() {
+        let client = builder("https://btcpay.example.com")
+            .merchant("merchant")
+            .api_key("key")
+            .network(Network::Testnet)
+            .retry_policy(RetryPolicy::none())
+            .default_header(
+                header::ACCEPT,
+                header::HeaderValue::from_static("text/plain"),
+            )
+            .build()
+            .unwrap();
+
+        assert_eq!(client.token.as_deref(), Some("merchant"));
+        assert_eq!(client.api_key.as_deref(), Some("key"));
+        assert_eq!(client.network(), Some(Network::Testnet));
+        assert_eq!(client.retry_policy.max_attempts, 1);
+        // Headers set on the builder take precedence over the defaults
+        assert_eq!(client.headers[header::ACCEPT], "text/plain");
+    }
+
+    #[test]
+    fn test_invalid_host() {
+        for host in &[
+            "",
+            "btcpay.example.com",
+            "ftp://btcpay.example.com",
+            "https://",
+        ] {
+            assert!(
+                matches!(builder(host).build(), Err(Error::InvalidHost(ref h)) if h == host),
+                "{}",
+                host
+            );
+        }
+
+        let client = BTCPayClient::new(
+            "btcpay.example.com/",
+            Cryptography::generate_keypair(),
+            None,
+        )
+        .unwrap();
+        assert_eq!(client.host, "https://btcpay.example.com");
+        assert!(BTCPayClient::new(
+            "ftp://btcpay.example.com",
+            Cryptography::generate_keypair(),
+            None
+        )
+        .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_timeout() {
+        let mut server = Server::new_async().await;
+        server
+            .mock("GET", "/api/v1/health")
+            .with_status_code_from_request(|_| {
+                std::thread::sleep(Duration::from_millis(300));
+                200
+            })
+            .with_body(r#"{"synchronized":true}"#)
+            .create_async()
+            .await;
+
+        let client = builder(&server.url())
+            .timeout(Duration::from_millis(50))
+            .retry_policy(RetryPolicy::none())
+            .build()
+            .unwrap();
+        assert!(matches!(client.get_health().await, Err(Error::Request(ref e)) if e.is_timeout()));
+    }
+
+    #[tokio::test]
+    async fn test_proxy_and_user_agent() {
+        let mut proxy = Server::new_async().await;
+        let mock = proxy
+            .mock("GET", "/api/v1/health")
+            .match_header("host", "btcpay.invalid")
+            .match_header("user-agent", "shop/1.0")
+            .match_header("x-shop", "1")
+            .match_header("authorization", Matcher::Missing)
+            .with_body(r#"{"synchronized":true}"#)
+            .create_async()
+            .await;
+
+        let client = builder("http://btcpay.invalid")
+            .proxy(Proxy::http(proxy.url()).unwrap())
+            .user_agent("shop/1.0")
+            .default_header(
+                header::HeaderName::from_static("x-shop"),
+                header::HeaderValue::from_static("1"),
+            )
+            .build()
+            .unwrap();
+        assert!(client.get_health().await.unwrap().synchronized);
+        mock.assert_async().await;
+    }
+}
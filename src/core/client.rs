@@ -1,51 +1,70 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, OnceLock};
 
+use bitcoin::Network;
+use futures_util::stream::{self, Stream, StreamExt};
 use regex::Regex;
-use reqwest::{header, Client, Response};
+use reqwest::{header, Client, RequestBuilder, Response};
 use serde::Serialize;
 
-use super::cryptography::{Cryptography, KeyPair};
+use super::builder::BTCPayClientBuilder;
+use super::retry::RetryPolicy;
+use super::signer::Signer;
 use crate::models::*;
 
 #[derive(Debug, Clone)]
 pub struct BTCPayClient {
-    host: String,
-    client_id: String,
-    token: Option<String>,
-    keypair: KeyPair,
-    client: Client,
+    pub(super) host: String,
+    pub(super) client_id: String,
+    pub(super) token: Option<String>,
+    pub(super) api_key: Option<String>,
+    pub(super) signer: Arc<dyn Signer>,
+    pub(super) client: Client,
+    pub(super) headers: header::HeaderMap,
+    pub(super) retry_policy: RetryPolicy,
+    pub(super) network: Arc<OnceLock<Network>>,
 }
 
 impl BTCPayClient {
-    pub fn new(host: &str, keypair: KeyPair, merchant: Option<&str>) -> Result<Self, Error> {
-        let token = merchant.map(String::from);
+    /// Unlike [`builder`](Self::builder), which requires an `http` or `https` URL, a host without
+    /// a scheme is still accepted here and assumed to be served over `https`
+    pub fn new<S: Signer + 'static>(
+        host: &str,
+        signer: S,
+        merchant: Option<&str>,
+    ) -> Result<Self, Error> {
+        let host = if host.contains("://") {
+            host.to_string()
+        } else {
+            format!("https://{}", host)
+        };
 
-        let mut headers = header::HeaderMap::new();
-        headers.insert(
-            header::CONTENT_TYPE,
-            header::HeaderValue::from_static("application/json"),
-        );
-        headers.insert(
-            header::ACCEPT,
-            header::HeaderValue::from_static("application/json"),
-        );
-        headers.insert(
-            "X-Accept-Version",
-            header::HeaderValue::from_static("2.0.0"),
-        );
+        let mut builder = BTCPayClientBuilder::new(&host, signer);
+        if let Some(token) = merchant {
+            builder = builder.merchant(token);
+        }
+
+        builder.build()
+    }
+
+    pub fn builder<S: Signer + 'static>(host: &str, signer: S) -> BTCPayClientBuilder {
+        BTCPayClientBuilder::new(host, signer)
+    }
 
-        let client = Client::builder()
-            .user_agent(concat!("rust-btcpay/", env!("CARGO_PKG_VERSION")))
-            .default_headers(headers)
-            .build()?;
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
 
-        Ok(BTCPayClient {
-            host: Regex::new(r"/+$").unwrap().replace(host, "").into(),
-            client_id: Cryptography::get_sin_from_key(&keypair),
-            token,
-            keypair,
-            client,
-        })
+    /// Network the server runs on, either configured on the builder or detected from the first
+    /// invoice received.
+    ///
+    /// Detection from [`get_server_info`](Self::get_server_info) is not supported: `/server/info`
+    /// only lists payment method ids like `BTC-CHAIN` and the sync heights, which are the same on
+    /// every network. Without a configured network this returns `None` until an invoice with a
+    /// BTC address or BOLT11 invoice has been fetched.
+    pub fn network(&self) -> Option<Network> {
+        self.network.get().copied()
     }
 
     pub async fn pair_client(&self, code: &str) -> Result<PairClientResponse, Error> {
@@ -76,16 +95,106 @@ impl BTCPayClient {
 
     pub async fn create_invoice(&self, args: CreateInvoiceArgs) -> Result<Invoice, Error> {
         if !Regex::new(r"^[A-Z]{3}$").unwrap().is_match(&args.currency) {
-            return Err(Error::InvalidCurrency(args.currency.into()));
+            return Err(Error::InvalidCurrency(args.currency));
         }
 
-        let mut intermediate = self
-            .signed_post_request("/invoices", &args)
-            .await?
-            .json::<serde_json::Value>()
+        // A failed POST may still have created the invoice, so it's only retried when the
+        // `order_id` lets us look for it before sending the request again
+        let mut attempt = 1;
+        let response = loop {
+            let result = self.signed_post_request("/invoices", &args).await;
+            if args.order_id.is_none() || !self.retry_policy.should_retry(&result, attempt) {
+                break result?;
+            }
+
+            tokio::time::sleep(self.retry_policy.backoff(attempt)).await;
+            attempt += 1;
+
+            if let Some(invoice) = self.find_created_invoice(&args).await? {
+                return Ok(invoice);
+            }
+        };
+
+        let mut intermediate = response.json::<serde_json::Value>().await?;
+
+        let invoice = serde_json::from_value(intermediate["data"].take())?;
+        self.check_network(&invoice)?;
+
+        Ok(invoice)
+    }
+
+    /// Pages through `get_invoices` until the results are exhausted.
+    ///
+    /// `args.offset` is used as the starting point and `args.limit` caps the total number of
+    /// invoices returned. Invoices that shift to the next page while paging are only yielded once.
+    pub fn invoices_stream(
+        &self,
+        args: GetInvoicesArgs,
+        page_size: usize,
+    ) -> impl Stream<Item = Result<Invoice, Error>> + '_ {
+        let page_size = page_size.max(1);
+        let limit = args.limit.unwrap_or(usize::MAX);
+        let offset = args.offset.unwrap_or(0);
+
+        stream::unfold(
+            Some((args, offset, HashSet::new())),
+            move |state| async move {
+                let (args, offset, mut seen) = state?;
+
+                let page = self
+                    .get_invoices(GetInvoicesArgs {
+                        limit: Some(page_size),
+                        offset: Some(offset),
+                        ..args.clone()
+                    })
+                    .await;
+
+                match page {
+                    Ok(invoices) => {
+                        let exhausted = invoices.len() < page_size;
+                        let fresh = invoices
+                            .into_iter()
+                            .filter(|invoice| seen.insert(invoice.id.clone()))
+                            .map(Ok)
+                            .collect::<Vec<_>>();
+
+                        let next = if exhausted {
+                            None
+                        } else {
+                            Some((args, offset + page_size, seen))
+                        };
+                        Some((fresh, next))
+                    }
+                    Err(e) => Some((vec![Err(e)], None)),
+                }
+            },
+        )
+        .flat_map(stream::iter)
+        .take(limit)
+    }
+
+    async fn find_created_invoice(
+        &self,
+        args: &CreateInvoiceArgs,
+    ) -> Result<Option<Invoice>, Error> {
+        let order_id = match &args.order_id {
+            Some(order_id) => order_id.to_string(),
+            None => return Ok(None),
+        };
+
+        let invoices = self
+            .get_invoices(GetInvoicesArgs {
+                order_id: Some(order_id.clone()),
+                ..Default::default()
+            })
             .await?;
 
-        Ok(serde_json::from_value(intermediate["data"].take())?)
+        // The price isn't compared: the server may round it or format it differently than the
+        // `f32` that was sent, and a mismatch would create the duplicate this lookup prevents
+        Ok(invoices.into_iter().find(|invoice| {
+            invoice.order_id.as_ref() == Some(&order_id)
+                && (args.pos_data.is_none() || invoice.pos_data == args.pos_data)
+        }))
     }
 
     pub async fn get_invoice(&self, invoice_id: &str) -> Result<Invoice, Error> {
@@ -98,38 +207,79 @@ impl BTCPayClient {
             .json::<serde_json::Value>()
             .await?;
 
-        Ok(serde_json::from_value(intermediate["data"].take())?)
+        let invoice = serde_json::from_value(intermediate["data"].take())?;
+        self.check_network(&invoice)?;
+
+        Ok(invoice)
     }
 
     pub async fn get_invoices(&self, args: GetInvoicesArgs) -> Result<Vec<Invoice>, Error> {
+        args.validate_legacy()?;
+
         let mut intermediate = self
             .signed_get_request("/invoices", &args)
             .await?
             .json::<serde_json::Value>()
             .await?;
 
+        let invoices: Vec<Invoice> = serde_json::from_value(intermediate["data"].take())?;
+        for invoice in &invoices {
+            self.check_network(invoice)?;
+        }
+
+        Ok(invoices)
+    }
+
+    /// Fetches the store rates for `currency_pairs`, like `BTC_USD`
+    pub async fn get_rates(&self, currency_pairs: &[&str]) -> Result<Vec<Rate>, Error> {
+        let mut params = HashMap::new();
+        params.insert("currencyPairs", currency_pairs.join(","));
+
+        let mut intermediate = self
+            .signed_get_request("/rates", &params)
+            .await?
+            .json::<serde_json::Value>()
+            .await?;
+
         Ok(serde_json::from_value(intermediate["data"].take())?)
     }
 
-    fn create_signed_headers(&self, uri: &str, payload: &str) -> header::HeaderMap {
+    /// Checks `invoice` against the configured network, or detects the network from its
+    /// destinations if none is known yet. The server can't be asked for it, see
+    /// [`network`](Self::network).
+    fn check_network(&self, invoice: &Invoice) -> Result<(), Error> {
+        let network = match self.network.get() {
+            Some(network) => *network,
+            None => match invoice.detect_network() {
+                Some(network) => *self.network.get_or_init(|| network),
+                None => return Ok(()),
+            },
+        };
+
+        invoice.check_network(network)
+    }
+
+    async fn create_signed_headers(
+        &self,
+        uri: &str,
+        payload: &str,
+    ) -> Result<header::HeaderMap, Error> {
+        let signature = self
+            .signer
+            .sign((uri.to_string() + payload).as_bytes())
+            .await?;
+
         let mut headers = header::HeaderMap::new();
         headers.insert(
             "X-Identity",
-            self.keypair.public.to_string().parse().unwrap(),
+            self.signer.public_key().to_string().parse().unwrap(),
         );
         headers.insert(
             "X-Signature",
-            Cryptography::sign(
-                (uri.to_string() + payload).as_bytes(),
-                self.keypair.secret(),
-            )
-            .unwrap()
-            .to_string()
-            .parse()
-            .unwrap(),
+            signature.serialize_der().to_string().parse().unwrap(),
         );
 
-        headers
+        Ok(headers)
     }
 
     async fn signed_get_request<T: Serialize>(
@@ -147,13 +297,17 @@ impl BTCPayClient {
         let query = serde_urlencoded::to_string(serialized.clone())?;
         let full_path = self.host.clone() + path;
 
-        Ok(self
+        let request = self
             .client
             .get(&full_path)
             .query(&serialized)
-            .headers(self.create_signed_headers(&full_path, &format!("?{}", query)))
-            .send()
-            .await?)
+            .headers(self.headers.clone())
+            .headers(
+                self.create_signed_headers(&full_path, &format!("?{}", query))
+                    .await?,
+            );
+
+        self.send_with_retry(request).await
     }
 
     async fn signed_post_request<T: Serialize>(
@@ -174,7 +328,8 @@ impl BTCPayClient {
         Ok(self
             .client
             .post(&full_path)
-            .headers(self.create_signed_headers(&full_path, &body))
+            .headers(self.headers.clone())
+            .headers(self.create_signed_headers(&full_path, &body).await?)
             .body(body)
             .send()
             .await?)
@@ -187,7 +342,31 @@ impl BTCPayClient {
     ) -> Result<Response, Error> {
         let full_path = self.host.clone() + path;
 
-        Ok(self.client.post(&full_path).json(payload).send().await?)
+        Ok(self
+            .client
+            .post(&full_path)
+            .headers(self.headers.clone())
+            .json(payload)
+            .send()
+            .await?)
+    }
+
+    pub(super) async fn send_with_retry(&self, request: RequestBuilder) -> Result<Response, Error> {
+        let mut attempt = 1;
+        loop {
+            let result = request
+                .try_clone()
+                .expect("request bodies are never streamed")
+                .send()
+                .await
+                .map_err(Error::from);
+            if !self.retry_policy.should_retry(&result, attempt) {
+                return result;
+            }
+
+            tokio::time::sleep(self.retry_policy.backoff(attempt)).await;
+            attempt += 1;
+        }
     }
 }
 
@@ -202,12 +381,40 @@ struct PairClientRequest {
 pub enum Error {
     InvalidPairingCode(String),
     InvalidCurrency(String),
+    InvalidHost(String),
+    InvalidFilter(String),
+    InvalidPaymentUri(String),
+    /// Not a valid Bitcoin address, on any network
+    InvalidAddress(String),
+    InconsistentPaymentMethods(String),
+    NetworkMismatch(Network, String),
+    IllegalTransition(InvoiceStatus, InvoiceStatus),
+    InvalidWebhookSignature,
+    WebhookNotConfigured(String),
+    WebhookCallback(String),
+    /// The same notification is still being processed by the callback
+    WebhookInProgress(String),
+    #[cfg(feature = "verify")]
+    ChainSource(String),
     MerchantTokenRequired,
+    ApiKeyRequired,
+    /// Error returned by the Greenfield API, with the HTTP status
+    Api(u16, String),
     InvalidResponse,
+    Signer(String),
+    UnknownStore(String),
+    DerivationIndexInUse(u32, String),
 
     Request(reqwest::Error),
     JSON(serde_json::Error),
     URLEncode(serde_urlencoded::ser::Error),
+    Bip32(bitcoin::bip32::Error),
+    Secp256k1(bitcoin::secp256k1::Error),
+    IO(std::io::Error),
+    #[cfg(feature = "qr")]
+    QrCode(qrcode::types::QrError),
+    #[cfg(feature = "qr")]
+    Png(png::EncodingError),
 }
 
 impl std::fmt::Display for Error {
@@ -235,3 +442,224 @@ impl From<serde_urlencoded::ser::Error> for Error {
         Error::URLEncode(other)
     }
 }
+
+impl From<bitcoin::bip32::Error> for Error {
+    fn from(other: bitcoin::bip32::Error) -> Error {
+        Error::Bip32(other)
+    }
+}
+
+impl From<bitcoin::secp256k1::Error> for Error {
+    fn from(other: bitcoin::secp256k1::Error) -> Error {
+        Error::Secp256k1(other)
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(other: std::io::Error) -> Error {
+        Error::IO(other)
+    }
+}
+
+#[cfg(feature = "qr")]
+impl From<qrcode::types::QrError> for Error {
+    fn from(other: qrcode::types::QrError) -> Error {
+        Error::QrCode(other)
+    }
+}
+
+#[cfg(feature = "qr")]
+impl From<png::EncodingError> for Error {
+    fn from(other: png::EncodingError) -> Error {
+        Error::Png(other)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use mockito::{Matcher, Server};
+
+    use super::*;
+    use crate::core::cryptography::Cryptography;
+    use crate::models::invoice::test::get_test_invoices_json;
+
+    #[tokio::test]
+    async fn test_create_invoice_retry_finds_existing() {
+        let mut server = Server::new_async().await;
+        // The server creates the invoice but answers after the client gave up
+        let post = server
+            .mock("POST", "/invoices")
+            .with_status_code_from_request(|_| {
+                std::thread::sleep(Duration::from_millis(200));
+                200
+            })
+            .with_body(r#"{"data":{}}"#)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let mut invoice: serde_json::Value =
+            serde_json::from_str(get_test_invoices_json()[1]).unwrap();
+        invoice["orderId"] = "A-1042".into();
+        // Not bit-for-bit the `f32` that was sent
+        invoice["price"] = 19.990000000000002.into();
+        let get = server
+            .mock("GET", "/invoices")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("orderId".into(), "A-1042".into()),
+                Matcher::UrlEncoded("token".into(), "merchant".into()),
+            ]))
+            .with_body(serde_json::json!({ "data": [invoice] }).to_string())
+            .create_async()
+            .await;
+
+        let client = BTCPayClient::builder(&server.url(), Cryptography::generate_keypair())
+            .merchant("merchant")
+            .timeout(Duration::from_millis(100))
+            .retry_policy(RetryPolicy {
+                initial_backoff: Duration::from_millis(300),
+                ..Default::default()
+            })
+            .build()
+            .unwrap();
+
+        let created = client
+            .create_invoice(CreateInvoiceArgs {
+                order_id: Some(OrderId::String("A-1042".into())),
+                ..CreateInvoiceArgs::new("USD", 19.99)
+            })
+            .await
+            .unwrap();
+        assert_eq!(created.id, "3Wy4cKQEvmsBru5yccGbNn");
+
+        post.assert_async().await;
+        get.assert_async().await;
+    }
+
+    fn invoice_page(ids: &[&str]) -> String {
+        let invoices: Vec<serde_json::Value> = ids
+            .iter()
+            .map(|id| {
+                let mut invoice: serde_json::Value =
+                    serde_json::from_str(get_test_invoices_json()[1]).unwrap();
+                invoice["id"] = (*id).into();
+                invoice
+            })
+            .collect();
+
+        serde_json::json!({ "data": invoices }).to_string()
+    }
+
+    async fn mock_page(server: &mut mockito::Server, offset: usize, status: usize, body: &str) {
+        server
+            .mock("GET", "/invoices")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("limit".into(), "2".into()),
+                Matcher::UrlEncoded("offset".into(), offset.to_string()),
+            ]))
+            .with_status(status)
+            .with_body(body)
+            .create_async()
+            .await;
+    }
+
+    fn stream_client(url: &str) -> BTCPayClient {
+        BTCPayClient::builder(url, Cryptography::generate_keypair())
+            .merchant("merchant")
+            .retry_policy(RetryPolicy::none())
+            .build()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_invoices_stream() {
+        let mut server = Server::new_async().await;
+        mock_page(&mut server, 0, 200, &invoice_page(&["a", "b"])).await;
+        // "b" shifted to the second page because a new invoice was created meanwhile
+        mock_page(&mut server, 2, 200, &invoice_page(&["b", "c"])).await;
+        mock_page(&mut server, 4, 200, &invoice_page(&["d"])).await;
+
+        let client = stream_client(&server.url());
+        let ids: Vec<String> = client
+            .invoices_stream(GetInvoicesArgs::default(), 2)
+            .map(|invoice| invoice.unwrap().id)
+            .collect()
+            .await;
+        // Stops after the short page: a request for offset 6 wouldn't match any mock
+        assert_eq!(ids, vec!["a", "b", "c", "d"]);
+
+        let ids: Vec<String> = client
+            .invoices_stream(
+                GetInvoicesArgs {
+                    limit: Some(1),
+                    ..Default::default()
+                },
+                2,
+            )
+            .map(|invoice| invoice.unwrap().id)
+            .collect()
+            .await;
+        assert_eq!(ids, vec!["a"]);
+    }
+
+    #[tokio::test]
+    async fn test_invoices_stream_error() {
+        let mut server = Server::new_async().await;
+        mock_page(&mut server, 0, 200, &invoice_page(&["a", "b"])).await;
+        mock_page(&mut server, 2, 500, r#"{"error":"Internal Server Error"}"#).await;
+
+        let client = stream_client(&server.url());
+        let results: Vec<_> = client
+            .invoices_stream(GetInvoicesArgs::default(), 2)
+            .collect()
+            .await;
+
+        // The error is yielded once and ends the stream
+        assert_eq!(results.len(), 3);
+        assert!(results[..2].iter().all(Result::is_ok));
+        assert!(results[2].is_err());
+    }
+
+    #[tokio::test]
+    async fn test_network_mismatch() {
+        let mut server = Server::new_async().await;
+        let testnet_invoice = format!(r#"{{"data":{}}}"#, get_test_invoices_json()[1]);
+        server
+            .mock("GET", "/invoices/3Wy4cKQEvmsBru5yccGbNn")
+            .match_query(Matcher::Any)
+            .with_body(&testnet_invoice)
+            .create_async()
+            .await;
+        server
+            .mock("POST", "/invoices")
+            .with_body(&testnet_invoice)
+            .create_async()
+            .await;
+
+        let mainnet = BTCPayClient::builder(&server.url(), Cryptography::generate_keypair())
+            .merchant("merchant")
+            .network(Network::Bitcoin)
+            .build()
+            .unwrap();
+        assert!(matches!(
+            mainnet.get_invoice("3Wy4cKQEvmsBru5yccGbNn").await,
+            Err(Error::NetworkMismatch(Network::Bitcoin, _))
+        ));
+        assert!(matches!(
+            mainnet
+                .create_invoice(CreateInvoiceArgs::new("USD", 20.0))
+                .await,
+            Err(Error::NetworkMismatch(Network::Bitcoin, _))
+        ));
+
+        // Without a configured network, the first invoice decides it
+        let detected = stream_client(&server.url());
+        detected
+            .get_invoice("3Wy4cKQEvmsBru5yccGbNn")
+            .await
+            .unwrap();
+        assert_eq!(detected.network(), Some(Network::Testnet));
+    }
+}
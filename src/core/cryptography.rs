@@ -1,6 +1,7 @@
 use std::ops::Deref;
 
 use bitcoin::base58;
+use bitcoin::bip32::{DerivationPath, Error as Bip32Error, ExtendedPrivKey};
 
 use bitcoin::secp256k1::{
     ecdsa::SerializedSignature, Error as Secp256k1Error, Message, PublicKey, Secp256k1, SecretKey,
@@ -29,6 +30,15 @@ impl Cryptography {
         }
     }
 
+    pub fn derive_keypair(
+        master: &ExtendedPrivKey,
+        path: &DerivationPath,
+    ) -> Result<KeyPair, Bip32Error> {
+        let derived = master.derive_priv(&Secp256k1::signing_only(), path)?;
+
+        Ok(Self::load_keypair(derived.private_key))
+    }
+
     pub fn get_sin_from_key(public_key: &PublicKey) -> String {
         let version = Self::get_version_from_key(public_key);
         let checksum = Self::get_checksum_from_version(&version);
@@ -57,9 +67,7 @@ impl Cryptography {
         let mut ans = [0; 22];
         ans[0] = 0x0F;
         ans[1] = 0x02;
-        for i in 2..22 {
-            ans[i] = rp[i - 2];
-        }
+        ans[2..22].copy_from_slice(&rp);
 
         ans
     }
@@ -69,9 +77,7 @@ impl Cryptography {
         let h2 = Sha256Hash::hash(&h1).to_byte_array();
 
         let mut ans = [0; 4];
-        for i in 0..4 {
-            ans[i] = h2[i];
-        }
+        ans.copy_from_slice(&h2[..4]);
 
         ans
     }
@@ -127,4 +133,21 @@ mod test {
         let sin = Cryptography::get_sin_from_key(&keypair);
         assert_eq!(sin, "TfDnXWvj6bBhkduYiZnohg5qhtDu5VWohhw")
     }
+
+    #[test]
+    fn test_derive_keypair() {
+        let master = ExtendedPrivKey::new_master(
+            bitcoin::Network::Bitcoin,
+            &Vec::<u8>::from_hex(MY_PRIVATE_KEY).unwrap(),
+        )
+        .unwrap();
+        let path: DerivationPath = "m/0'/1'".parse().unwrap();
+
+        let first = Cryptography::derive_keypair(&master, &path).unwrap();
+        let second = Cryptography::derive_keypair(&master, &path).unwrap();
+        assert_eq!(first.public, second.public);
+
+        let other = Cryptography::derive_keypair(&master, &"m/0'/2'".parse().unwrap()).unwrap();
+        assert_ne!(first.public, other.public);
+    }
 }
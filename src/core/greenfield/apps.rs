@@ -0,0 +1,257 @@
+use std::collections::HashMap;
+
+use super::encode_segment;
+use crate::core::client::{BTCPayClient, Error};
+use crate::models::*;
+
+impl BTCPayClient {
+    /// Apps of every type in a store
+    pub async fn get_apps(&self, store_id: &str) -> Result<Vec<App>, Error> {
+        self.greenfield_get(&format!("/stores/{}/apps", encode_segment(store_id)))
+            .await
+    }
+
+    pub async fn delete_app(&self, app_id: &str) -> Result<(), Error> {
+        self.greenfield_delete(&format!("/apps/{}", encode_segment(app_id)))
+            .await
+    }
+
+    pub async fn create_pos_app(
+        &self,
+        store_id: &str,
+        request: &PointOfSaleAppRequest,
+    ) -> Result<PointOfSaleApp, Error> {
+        self.greenfield_post(
+            &format!("/stores/{}/apps/pos", encode_segment(store_id)),
+            request,
+        )
+        .await
+    }
+
+    pub async fn get_pos_app(&self, app_id: &str) -> Result<PointOfSaleApp, Error> {
+        self.greenfield_get(&format!("/apps/pos/{}", encode_segment(app_id)))
+            .await
+    }
+
+    pub async fn update_pos_app(
+        &self,
+        app_id: &str,
+        request: &PointOfSaleAppRequest,
+    ) -> Result<PointOfSaleApp, Error> {
+        self.greenfield_put(&format!("/apps/pos/{}", encode_segment(app_id)), request)
+            .await
+    }
+
+    pub async fn create_crowdfund_app(
+        &self,
+        store_id: &str,
+        request: &CrowdfundAppRequest,
+    ) -> Result<CrowdfundApp, Error> {
+        self.greenfield_post(
+            &format!("/stores/{}/apps/crowdfund", encode_segment(store_id)),
+            request,
+        )
+        .await
+    }
+
+    pub async fn get_crowdfund_app(&self, app_id: &str) -> Result<CrowdfundApp, Error> {
+        self.greenfield_get(&format!("/apps/crowdfund/{}", encode_segment(app_id)))
+            .await
+    }
+
+    pub async fn update_crowdfund_app(
+        &self,
+        app_id: &str,
+        request: &CrowdfundAppRequest,
+    ) -> Result<CrowdfundApp, Error> {
+        self.greenfield_put(
+            &format!("/apps/crowdfund/{}", encode_segment(app_id)),
+            request,
+        )
+        .await
+    }
+
+    /// Sales of an app over the last `number_of_days`, 7 by default on the server
+    pub async fn get_app_sales(
+        &self,
+        app_id: &str,
+        number_of_days: Option<u32>,
+    ) -> Result<AppSales, Error> {
+        let mut query = HashMap::new();
+        if let Some(number_of_days) = number_of_days {
+            query.insert("numberOfDays", number_of_days);
+        }
+
+        self.greenfield_get_query(&format!("/apps/{}/sales", encode_segment(app_id)), &query)
+            .await
+    }
+
+    /// Best selling items of an app, from `offset`. `count` defaults to 10 on the server.
+    pub async fn get_app_top_items(
+        &self,
+        app_id: &str,
+        offset: usize,
+        count: Option<usize>,
+    ) -> Result<Vec<AppItemStats>, Error> {
+        let mut query = HashMap::new();
+        query.insert("offset", offset);
+        if let Some(count) = count {
+            query.insert("count", count);
+        }
+
+        self.greenfield_get_query(
+            &format!("/apps/{}/top-items", encode_segment(app_id)),
+            &query,
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use mockito::{Matcher, Server};
+    use rust_decimal::Decimal;
+
+    use super::*;
+    use crate::core::greenfield::test::{json_body, mock_endpoint, test_client};
+    use crate::models::app::test::{CROWDFUND_APP_JSON, POS_APP_JSON};
+    use crate::models::timestamp;
+
+    #[tokio::test]
+    async fn test_get_apps() {
+        let mut server = Server::new_async().await;
+        let mock = mock_endpoint(&mut server, "GET", "/stores/store/apps")
+            .with_body(format!("[{},{}]", POS_APP_JSON, CROWDFUND_APP_JSON))
+            .create_async()
+            .await;
+
+        let apps = test_client(&server.url()).get_apps("store").await.unwrap();
+        assert_eq!(
+            apps.iter().map(|app| &app.app_type).collect::<Vec<_>>(),
+            vec![&AppType::PointOfSale, &AppType::Crowdfund]
+        );
+        assert_eq!(apps[1].app_name, "Roof");
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_pos_app() {
+        let mut server = Server::new_async().await;
+        let create = mock_endpoint(&mut server, "POST", "/stores/store/apps/pos")
+            .match_body(json_body(
+                r#"{"appName":"Shop","currency":"EUR","template":"[{\"id\":\"coffee\",\"title\":\"Coffee\",\"price\":2.5,\"priceType\":\"Fixed\",\"disabled\":false}]"}"#,
+            ))
+            .with_body(POS_APP_JSON)
+            .create_async()
+            .await;
+        let get = mock_endpoint(&mut server, "GET", "/apps/pos/app")
+            .with_body(POS_APP_JSON)
+            .create_async()
+            .await;
+        let delete = mock_endpoint(&mut server, "DELETE", "/apps/app")
+            .create_async()
+            .await;
+
+        let client = test_client(&server.url());
+        let request = PointOfSaleAppRequest {
+            currency: Some("EUR".into()),
+            items: Some(vec![AppItem::new("coffee", "Coffee", Decimal::new(25, 1))]),
+            ..PointOfSaleAppRequest::new("Shop")
+        };
+        let app = client.create_pos_app("store", &request).await.unwrap();
+        assert_eq!(app.app.app_type, AppType::PointOfSale);
+        let app = client.get_pos_app("app").await.unwrap();
+        assert_eq!(app.items[0].price, Some(Decimal::new(25, 1)));
+        client.delete_app("app").await.unwrap();
+
+        create.assert_async().await;
+        get.assert_async().await;
+        delete.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_crowdfund_app() {
+        let mut server = Server::new_async().await;
+        let create = mock_endpoint(&mut server, "POST", "/stores/store/apps/crowdfund")
+            .match_body(json_body(
+                r#"{"appName":"Roof","targetCurrency":"USD","targetAmount":5000.0,"endDate":1692293687}"#,
+            ))
+            .with_body(CROWDFUND_APP_JSON)
+            .create_async()
+            .await;
+        let get = mock_endpoint(&mut server, "GET", "/apps/crowdfund/app")
+            .with_body(CROWDFUND_APP_JSON)
+            .create_async()
+            .await;
+        let delete = mock_endpoint(&mut server, "DELETE", "/apps/app")
+            .create_async()
+            .await;
+
+        let client = test_client(&server.url());
+        let request = CrowdfundAppRequest {
+            target_currency: Some("USD".into()),
+            target_amount: Some(5000.0),
+            end_date: Some(timestamp::from_millis(1692293687000)),
+            ..CrowdfundAppRequest::new("Roof")
+        };
+        let app = client
+            .create_crowdfund_app("store", &request)
+            .await
+            .unwrap();
+        assert_eq!(app.app.app_type, AppType::Crowdfund);
+        let app = client.get_crowdfund_app("app").await.unwrap();
+        assert_eq!(app.reset_every, Some(ResetEvery::Never));
+        assert_eq!(app.perks[0].price, Some(Decimal::new(25, 0)));
+        client.delete_app("app").await.unwrap();
+
+        create.assert_async().await;
+        get.assert_async().await;
+        delete.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_update_pos_app() {
+        let mut server = Server::new_async().await;
+        let mock = mock_endpoint(&mut server, "PUT", "/apps/pos/app")
+            .match_body(Matcher::PartialJsonString(
+                r#"{"appName":"Shop","template":"[{\"id\":\"coffee\",\"title\":\"Coffee\",\"price\":2.5,\"priceType\":\"Fixed\",\"disabled\":false}]"}"#
+                    .to_string(),
+            ))
+            .with_body(POS_APP_JSON)
+            .create_async()
+            .await;
+
+        let app = test_client(&server.url())
+            .update_pos_app(
+                "app",
+                &PointOfSaleAppRequest {
+                    items: Some(vec![AppItem::new("coffee", "Coffee", Decimal::new(25, 1))]),
+                    ..PointOfSaleAppRequest::new("Shop")
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(app.items.len(), 2);
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_get_app_sales() {
+        let mut server = Server::new_async().await;
+        let mock = mock_endpoint(&mut server, "GET", "/apps/app/sales")
+            .match_query("numberOfDays=2")
+            .with_body(
+                r#"{"salesCount":12,"series":[{"date":1689552000,"label":"Mon","salesCount":5},{"date":1689638400,"label":"Tue","salesCount":7}]}"#,
+            )
+            .create_async()
+            .await;
+
+        let sales = test_client(&server.url())
+            .get_app_sales("app", Some(2))
+            .await
+            .unwrap();
+        assert_eq!(sales.sales_count, 12);
+        assert_eq!(sales.series[1].label, "Tue");
+        mock.assert_async().await;
+    }
+}
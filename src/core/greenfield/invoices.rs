@@ -0,0 +1,333 @@
+use serde::Serialize;
+
+use super::encode_segment;
+use crate::core::client::{BTCPayClient, Error};
+use crate::models::*;
+
+impl BTCPayClient {
+    pub async fn create_greenfield_invoice<T: Serialize>(
+        &self,
+        store_id: &str,
+        request: &CreateGreenfieldInvoiceRequest<T>,
+    ) -> Result<GreenfieldInvoice, Error> {
+        self.greenfield_post(
+            &format!("/stores/{}/invoices", encode_segment(store_id)),
+            request,
+        )
+        .await
+    }
+
+    pub async fn get_greenfield_invoice(
+        &self,
+        store_id: &str,
+        invoice_id: &str,
+    ) -> Result<GreenfieldInvoice, Error> {
+        self.greenfield_get(&format!(
+            "/stores/{}/invoices/{}",
+            encode_segment(store_id),
+            encode_segment(invoice_id)
+        ))
+        .await
+    }
+
+    /// Invoices of `args.store_id`, most recent first. Paginate with `offset` and `limit`.
+    pub async fn get_greenfield_invoices(
+        &self,
+        args: &GetInvoicesArgs,
+    ) -> Result<Vec<GreenfieldInvoice>, Error> {
+        let query = args.greenfield_query()?;
+        let store_id = args.store_id.as_deref().unwrap_or_default();
+
+        self.greenfield_get_query(
+            &format!("/stores/{}/invoices", encode_segment(store_id)),
+            &query,
+        )
+        .await
+    }
+
+    pub async fn mark_invoice_status(
+        &self,
+        store_id: &str,
+        invoice_id: &str,
+        status: MarkInvoiceStatus,
+    ) -> Result<GreenfieldInvoice, Error> {
+        self.greenfield_post(
+            &format!(
+                "/stores/{}/invoices/{}/status",
+                encode_segment(store_id),
+                encode_segment(invoice_id)
+            ),
+            &MarkInvoiceStatusRequest { status },
+        )
+        .await
+    }
+
+    /// Hides an invoice from the invoice list. The server doesn't return the invoice, so it is
+    /// fetched back with a second request.
+    pub async fn archive_invoice(
+        &self,
+        store_id: &str,
+        invoice_id: &str,
+    ) -> Result<GreenfieldInvoice, Error> {
+        self.greenfield_delete(&format!(
+            "/stores/{}/invoices/{}",
+            encode_segment(store_id),
+            encode_segment(invoice_id)
+        ))
+        .await?;
+
+        self.get_greenfield_invoice(store_id, invoice_id).await
+    }
+
+    /// Same as [`archive_invoice`](Self::archive_invoice), the invoice is fetched back with a
+    /// second request
+    pub async fn unarchive_invoice(
+        &self,
+        store_id: &str,
+        invoice_id: &str,
+    ) -> Result<GreenfieldInvoice, Error> {
+        self.greenfield_post_empty(
+            &format!(
+                "/stores/{}/invoices/{}/unarchive",
+                encode_segment(store_id),
+                encode_segment(invoice_id)
+            ),
+            None::<&()>,
+        )
+        .await?;
+
+        self.get_greenfield_invoice(store_id, invoice_id).await
+    }
+
+    /// Activates a payment method that the store creates lazily, like generating the Lightning
+    /// invoice only when the buyer picks it. The invoice is fetched back with a second request.
+    pub async fn activate_payment_method(
+        &self,
+        store_id: &str,
+        invoice_id: &str,
+        payment_method: &PaymentMethodId,
+    ) -> Result<GreenfieldInvoice, Error> {
+        self.greenfield_post_empty(
+            &format!(
+                "/stores/{}/invoices/{}/payment-methods/{}/activate",
+                encode_segment(store_id),
+                encode_segment(invoice_id),
+                encode_segment(&payment_method.to_greenfield_id())
+            ),
+            None::<&()>,
+        )
+        .await?;
+
+        self.get_greenfield_invoice(store_id, invoice_id).await
+    }
+
+    /// Payment methods of an invoice, with the payments received through each of them. See
+    /// `InvoicePaymentSummary` for what is due, paid and pending confirmation.
+    pub async fn get_invoice_payment_methods(
+        &self,
+        store_id: &str,
+        invoice_id: &str,
+    ) -> Result<Vec<InvoicePaymentMethod>, Error> {
+        self.greenfield_get(&format!(
+            "/stores/{}/invoices/{}/payment-methods",
+            encode_segment(store_id),
+            encode_segment(invoice_id)
+        ))
+        .await
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct MarkInvoiceStatusRequest {
+    status: MarkInvoiceStatus,
+}
+
+#[cfg(test)]
+mod test {
+    use mockito::{Matcher, Server};
+    use rust_decimal::Decimal;
+
+    use super::*;
+    use crate::core::greenfield::test::{json_body, mock_endpoint, test_client};
+    use crate::models::greenfield_invoice::test::INVOICE_JSON;
+    use crate::models::invoice_payment_method::test::PAYMENT_METHODS_JSON;
+    use crate::models::timestamp;
+
+    #[tokio::test]
+    async fn test_create_greenfield_invoice() {
+        let mut server = Server::new_async().await;
+        let mock = mock_endpoint(&mut server, "POST", "/stores/store/invoices")
+            .match_body(json_body(
+                r#"{"amount":"20.00","currency":"USD","metadata":{"orderId":"A-1042"}}"#,
+            ))
+            .with_body(INVOICE_JSON)
+            .create_async()
+            .await;
+
+        let metadata = InvoiceMetadata {
+            order_id: Some("A-1042".into()),
+            ..Default::default()
+        };
+        let invoice = test_client(&server.url())
+            .create_greenfield_invoice(
+                "store",
+                &CreateGreenfieldInvoiceRequest::new(Decimal::new(2000, 2), "USD")
+                    .metadata(metadata),
+            )
+            .await
+            .unwrap();
+        assert_eq!(invoice.id, "PJuNNh4AZsfGbLqsLP1Ekq");
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_path_segments_are_encoded() {
+        let mut server = Server::new_async().await;
+        let mock = mock_endpoint(
+            &mut server,
+            "GET",
+            "/stores/my%20store/invoices/..%2Finvoices%3Fstatus%3DNew",
+        )
+        .with_body(INVOICE_JSON)
+        .create_async()
+        .await;
+
+        test_client(&server.url())
+            .get_greenfield_invoice("my store", "../invoices?status=New")
+            .await
+            .unwrap();
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_get_greenfield_invoices() {
+        let mut server = Server::new_async().await;
+        let mock = mock_endpoint(&mut server, "GET", "/stores/store/invoices")
+            .match_query(
+                "orderId=A-1042&textSearch=coffee&status=New&status=Expired\
+                 &exceptionStatus=PaidLate&includeArchived=false&startDate=1587907315&skip=50&take=25",
+            )
+            .with_body(format!("[{}]", INVOICE_JSON))
+            .create_async()
+            .await;
+
+        let args = GetInvoicesArgs {
+            store_id: Some("store".into()),
+            order_id: Some("A-1042".into()),
+            text_search: Some("coffee".into()),
+            status: vec![InvoiceStatus::Expired, InvoiceStatus::New]
+                .into_iter()
+                .collect(),
+            exception_status: Some(ExceptionStatus::PaidLate),
+            include_archived: Some(false),
+            date_start: Some(timestamp::from_millis(1587907315000)),
+            offset: Some(50),
+            limit: Some(25),
+            ..Default::default()
+        };
+        let invoices = test_client(&server.url())
+            .get_greenfield_invoices(&args)
+            .await
+            .unwrap();
+        assert_eq!(invoices.len(), 1);
+        mock.assert_async().await;
+
+        let error = test_client(&server.url())
+            .get_greenfield_invoices(&GetInvoicesArgs::default())
+            .await
+            .unwrap_err();
+        assert!(matches!(error, Error::InvalidFilter(_)));
+    }
+
+    #[tokio::test]
+    async fn test_mark_invoice_status() {
+        let mut server = Server::new_async().await;
+        let mock = mock_endpoint(&mut server, "POST", "/stores/store/invoices/invoice/status")
+            .match_body(json_body(r#"{"status":"Invalid"}"#))
+            .with_body(INVOICE_JSON)
+            .create_async()
+            .await;
+
+        test_client(&server.url())
+            .mark_invoice_status("store", "invoice", MarkInvoiceStatus::Invalid)
+            .await
+            .unwrap();
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_archive_and_unarchive_invoice() {
+        let mut server = Server::new_async().await;
+        let archive = mock_endpoint(&mut server, "DELETE", "/stores/store/invoices/invoice")
+            .create_async()
+            .await;
+        let unarchive = mock_endpoint(
+            &mut server,
+            "POST",
+            "/stores/store/invoices/invoice/unarchive",
+        )
+        .match_body(Matcher::Exact(String::new()))
+        .create_async()
+        .await;
+        let get = mock_endpoint(&mut server, "GET", "/stores/store/invoices/invoice")
+            .with_body(INVOICE_JSON)
+            .expect(2)
+            .create_async()
+            .await;
+
+        let client = test_client(&server.url());
+        client.archive_invoice("store", "invoice").await.unwrap();
+        let invoice = client.unarchive_invoice("store", "invoice").await.unwrap();
+        assert_eq!(invoice.id, "PJuNNh4AZsfGbLqsLP1Ekq");
+        archive.assert_async().await;
+        unarchive.assert_async().await;
+        get.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_activate_payment_method() {
+        let mut server = Server::new_async().await;
+        let activate = mock_endpoint(
+            &mut server,
+            "POST",
+            "/stores/store/invoices/invoice/payment-methods/BTC-LN/activate",
+        )
+        .create_async()
+        .await;
+        let get = mock_endpoint(&mut server, "GET", "/stores/store/invoices/invoice")
+            .with_body(INVOICE_JSON)
+            .create_async()
+            .await;
+
+        let invoice = test_client(&server.url())
+            .activate_payment_method("store", "invoice", &PaymentMethodId::lightning("BTC"))
+            .await
+            .unwrap();
+        assert_eq!(invoice.id, "PJuNNh4AZsfGbLqsLP1Ekq");
+        activate.assert_async().await;
+        get.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_get_invoice_payment_methods() {
+        let mut server = Server::new_async().await;
+        let mock = mock_endpoint(
+            &mut server,
+            "GET",
+            "/stores/store/invoices/invoice/payment-methods",
+        )
+        .with_body(PAYMENT_METHODS_JSON)
+        .create_async()
+        .await;
+
+        let methods = test_client(&server.url())
+            .get_invoice_payment_methods("store", "invoice")
+            .await
+            .unwrap();
+        assert_eq!(methods.len(), 2);
+        assert!(InvoicePaymentSummary::new(&methods)
+            .unwrap()
+            .is_partially_paid());
+        mock.assert_async().await;
+    }
+}
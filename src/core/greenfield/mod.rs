@@ -0,0 +1,221 @@
+//! Greenfield API (`/api/v1`), authenticated with the API key set on the builder.
+
+use percent_encoding::{utf8_percent_encode, AsciiSet, PercentEncode, NON_ALPHANUMERIC};
+use reqwest::{header, Method, RequestBuilder, Response};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use super::client::{BTCPayClient, Error};
+
+mod apps;
+mod invoices;
+mod notifications;
+mod server;
+mod users;
+mod webhooks;
+
+/// Everything but the unreserved characters of RFC 3986
+const PATH_SEGMENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+/// Encodes user input, like an email, to be used as a segment of a path
+fn encode_segment(segment: &str) -> PercentEncode<'_> {
+    utf8_percent_encode(segment, PATH_SEGMENT)
+}
+
+impl BTCPayClient {
+    fn greenfield_request(&self, method: Method, path: &str) -> Result<RequestBuilder, Error> {
+        let api_key = self.api_key.as_ref().ok_or(Error::ApiKeyRequired)?;
+
+        Ok(self
+            .client
+            .request(method, format!("{}/api/v1{}", self.host, path))
+            .headers(self.headers.clone())
+            .header(header::AUTHORIZATION, format!("token {}", api_key)))
+    }
+
+    /// Sends `request`, retrying it only if it's safe to send again
+    async fn greenfield_send(
+        &self,
+        request: RequestBuilder,
+        idempotent: bool,
+    ) -> Result<Response, Error> {
+        let response = if idempotent {
+            self.send_with_retry(request).await?
+        } else {
+            request.send().await?
+        };
+
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+
+        Err(api_error(status.as_u16(), &response.text().await?))
+    }
+
+    /// For the few endpoints that don't require an API key
+    async fn greenfield_get_public<T: DeserializeOwned>(&self, path: &str) -> Result<T, Error> {
+        let request = self
+            .client
+            .get(format!("{}/api/v1{}", self.host, path))
+            .headers(self.headers.clone());
+
+        Ok(self.greenfield_send(request, true).await?.json().await?)
+    }
+
+    async fn greenfield_get<T: DeserializeOwned>(&self, path: &str) -> Result<T, Error> {
+        self.greenfield_get_query(path, &()).await
+    }
+
+    async fn greenfield_get_query<Q: Serialize + ?Sized, T: DeserializeOwned>(
+        &self,
+        path: &str,
+        query: &Q,
+    ) -> Result<T, Error> {
+        let request = self.greenfield_request(Method::GET, path)?.query(query);
+
+        Ok(self.greenfield_send(request, true).await?.json().await?)
+    }
+
+    async fn greenfield_post<B: Serialize + ?Sized, T: DeserializeOwned>(
+        &self,
+        path: &str,
+        body: &B,
+    ) -> Result<T, Error> {
+        let request = self.greenfield_request(Method::POST, path)?.json(body);
+
+        Ok(self.greenfield_send(request, false).await?.json().await?)
+    }
+
+    async fn greenfield_put<B: Serialize + ?Sized, T: DeserializeOwned>(
+        &self,
+        path: &str,
+        body: &B,
+    ) -> Result<T, Error> {
+        let request = self.greenfield_request(Method::PUT, path)?.json(body);
+
+        Ok(self.greenfield_send(request, true).await?.json().await?)
+    }
+
+    /// For endpoints that answer without a body. Without `body` the request has none either,
+    /// rather than a JSON `null`.
+    async fn greenfield_post_empty<B: Serialize + ?Sized>(
+        &self,
+        path: &str,
+        body: Option<&B>,
+    ) -> Result<(), Error> {
+        let mut request = self.greenfield_request(Method::POST, path)?;
+        if let Some(body) = body {
+            request = request.json(body);
+        }
+        self.greenfield_send(request, false).await?;
+
+        Ok(())
+    }
+
+    async fn greenfield_delete(&self, path: &str) -> Result<(), Error> {
+        let request = self.greenfield_request(Method::DELETE, path)?;
+        self.greenfield_send(request, true).await?;
+
+        Ok(())
+    }
+}
+
+/// Builds an error from the body of a failed response: either `{"code", "message"}` or, for
+/// validation errors, a list of `{"path", "message"}`
+fn api_error(status: u16, body: &str) -> Error {
+    let message = match serde_json::from_str::<serde_json::Value>(body) {
+        Ok(serde_json::Value::Array(errors)) => errors
+            .iter()
+            .map(|e| match e["path"].as_str() {
+                Some(path) => format!("{}: {}", path, e["message"].as_str().unwrap_or_default()),
+                None => e["message"].as_str().unwrap_or_default().to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join(", "),
+        Ok(error) => match error["message"].as_str() {
+            Some(message) => message.to_string(),
+            None => body.to_string(),
+        },
+        Err(_) => body.to_string(),
+    };
+
+    Error::Api(status, message)
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use mockito::{Matcher, Mock, Server};
+
+    use super::*;
+    use crate::core::cryptography::Cryptography;
+
+    pub const API_KEY: &str = "6d3ad6b4d5c04a6a4a2b2e5b7f1f0e7c1d6c3a2b";
+
+    /// Mocks a Greenfield endpoint answering with JSON, only matched by requests authenticated
+    /// with [`API_KEY`]
+    pub fn mock_endpoint(server: &mut Server, method: &str, path: &str) -> Mock {
+        server
+            .mock(method, format!("/api/v1{}", path).as_str())
+            .match_header("authorization", format!("token {}", API_KEY).as_str())
+            .with_header("content-type", "application/json")
+    }
+
+    /// Matches a JSON request body, ignoring the formatting
+    pub fn json_body(body: &str) -> Matcher {
+        Matcher::JsonString(body.to_string())
+    }
+
+    pub fn test_client(url: &str) -> BTCPayClient {
+        BTCPayClient::builder(url, Cryptography::generate_keypair())
+            .api_key(API_KEY)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_api_error() {
+        let error = api_error(
+            404,
+            r#"{"code":"webhook-not-found","message":"The webhook was not found"}"#,
+        );
+        assert!(matches!(error, Error::Api(404, ref m) if m == "The webhook was not found"));
+
+        let error = api_error(
+            422,
+            r#"[{"path":"url","message":"Invalid url"},{"path":"secret","message":"Too short"}]"#,
+        );
+        assert!(
+            matches!(error, Error::Api(422, ref m) if m == "url: Invalid url, secret: Too short")
+        );
+
+        assert!(
+            matches!(api_error(502, "Bad Gateway"), Error::Api(502, ref m) if m == "Bad Gateway")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_greenfield_request() {
+        let mut server = Server::new_async().await;
+        let mock = mock_endpoint(&mut server, "GET", "/stores")
+            .with_status(403)
+            .with_body(r#"{"code":"missing-permission","message":"Missing permission"}"#)
+            .create_async()
+            .await;
+
+        let result: Result<serde_json::Value, _> =
+            test_client(&server.url()).greenfield_get("/stores").await;
+        assert!(matches!(result, Err(Error::Api(403, ref m)) if m == "Missing permission"));
+        mock.assert_async().await;
+
+        let client = BTCPayClient::builder(&server.url(), Cryptography::generate_keypair())
+            .build()
+            .unwrap();
+        let result: Result<serde_json::Value, _> = client.greenfield_get("/stores").await;
+        assert!(matches!(result, Err(Error::ApiKeyRequired)));
+    }
+}
@@ -0,0 +1,114 @@
+use serde::Serialize;
+
+use super::encode_segment;
+use crate::core::client::{BTCPayClient, Error};
+use crate::models::*;
+
+impl BTCPayClient {
+    /// Notifications of the user owning the API key, most recent first
+    pub async fn get_notifications(
+        &self,
+        args: &GetNotificationsArgs,
+    ) -> Result<Vec<Notification>, Error> {
+        self.greenfield_get_query("/users/me/notifications", args)
+            .await
+    }
+
+    pub async fn get_notification(&self, notification_id: &str) -> Result<Notification, Error> {
+        self.greenfield_get(&format!(
+            "/users/me/notifications/{}",
+            encode_segment(notification_id)
+        ))
+        .await
+    }
+
+    /// Marks a notification as seen or unseen. Always explicit: the server toggles the flag when
+    /// it's omitted, which would not be safe to retry.
+    pub async fn update_notification(
+        &self,
+        notification_id: &str,
+        seen: bool,
+    ) -> Result<Notification, Error> {
+        self.greenfield_put(
+            &format!(
+                "/users/me/notifications/{}",
+                encode_segment(notification_id)
+            ),
+            &UpdateNotificationRequest { seen },
+        )
+        .await
+    }
+
+    pub async fn mark_notification_seen(
+        &self,
+        notification_id: &str,
+    ) -> Result<Notification, Error> {
+        self.update_notification(notification_id, true).await
+    }
+
+    pub async fn delete_notification(&self, notification_id: &str) -> Result<(), Error> {
+        self.greenfield_delete(&format!(
+            "/users/me/notifications/{}",
+            encode_segment(notification_id)
+        ))
+        .await
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct UpdateNotificationRequest {
+    seen: bool,
+}
+
+#[cfg(test)]
+mod test {
+    use mockito::Server;
+
+    use super::*;
+    use crate::core::greenfield::test::{json_body, mock_endpoint, test_client};
+    use crate::models::notification::test::NOTIFICATIONS_JSON;
+    use crate::models::timestamp;
+
+    #[tokio::test]
+    async fn test_get_notifications() {
+        let mut server = Server::new_async().await;
+        let mock = mock_endpoint(&mut server, "GET", "/users/me/notifications")
+            .match_query("seen=false&take=3")
+            .with_body(NOTIFICATIONS_JSON)
+            .create_async()
+            .await;
+
+        let notifications = test_client(&server.url())
+            .get_notifications(&GetNotificationsArgs {
+                take: Some(3),
+                ..GetNotificationsArgs::unseen()
+            })
+            .await
+            .unwrap();
+        assert_eq!(notifications.len(), 3);
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_mark_notification_seen() {
+        let mut server = Server::new_async().await;
+        let mock = mock_endpoint(&mut server, "PUT", "/users/me/notifications/n")
+            .match_body(json_body(r#"{"seen":true}"#))
+            .with_body(
+                r#"{"id":"n","identifier":"newversion","type":"newversion","body":"New version 1.11.8 released!","storeId":null,"link":"https://github.com/btcpayserver/btcpayserver/releases/tag/v1.11.8","seen":true,"createdTime":1689615287}"#,
+            )
+            .create_async()
+            .await;
+
+        let notification = test_client(&server.url())
+            .mark_notification_seen("n")
+            .await
+            .unwrap();
+        assert!(notification.seen);
+        assert_eq!(
+            timestamp::to_millis(&notification.created_time),
+            1689615287000
+        );
+        mock.assert_async().await;
+    }
+}
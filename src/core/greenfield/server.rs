@@ -0,0 +1,57 @@
+use crate::core::client::{BTCPayClient, Error};
+use crate::models::*;
+
+impl BTCPayClient {
+    /// Whether the server is synchronized. Doesn't require an API key.
+    pub async fn get_health(&self) -> Result<HealthCheck, Error> {
+        self.greenfield_get_public("/health").await
+    }
+
+    /// Version, supported payment methods and synchronization state of each chain. Requires an API
+    /// key, unlike [`get_health`](Self::get_health). It doesn't say which network the server runs
+    /// on, see [`network`](Self::network).
+    pub async fn get_server_info(&self) -> Result<ServerInfo, Error> {
+        self.greenfield_get("/server/info").await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use mockito::{Matcher, Server};
+
+    use super::*;
+    use crate::core::cryptography::Cryptography;
+    use crate::core::greenfield::test::{mock_endpoint, test_client};
+    use crate::models::server::test::SERVER_INFO_JSON;
+
+    #[tokio::test]
+    async fn test_get_health() {
+        let mut server = Server::new_async().await;
+        let mock = server
+            .mock("GET", "/api/v1/health")
+            .match_header("authorization", Matcher::Missing)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"synchronized":true}"#)
+            .create_async()
+            .await;
+
+        let client = BTCPayClient::builder(&server.url(), Cryptography::generate_keypair())
+            .build()
+            .unwrap();
+        assert!(client.get_health().await.unwrap().synchronized);
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_get_server_info() {
+        let mut server = Server::new_async().await;
+        let mock = mock_endpoint(&mut server, "GET", "/server/info")
+            .with_body(SERVER_INFO_JSON)
+            .create_async()
+            .await;
+
+        let info = test_client(&server.url()).get_server_info().await.unwrap();
+        assert_eq!(info.version, "1.11.7");
+        mock.assert_async().await;
+    }
+}
@@ -0,0 +1,218 @@
+use serde::Serialize;
+
+use super::encode_segment;
+use crate::core::client::{BTCPayClient, Error};
+use crate::models::*;
+
+impl BTCPayClient {
+    /// Creates a user. Creating an administrator requires an administrator API key, except for the
+    /// first user of the server.
+    pub async fn create_user(&self, request: &CreateUserRequest) -> Result<ApplicationUser, Error> {
+        self.greenfield_post("/users", request).await
+    }
+
+    /// User owning the API key
+    pub async fn get_current_user(&self) -> Result<ApplicationUser, Error> {
+        self.greenfield_get("/users/me").await
+    }
+
+    pub async fn get_users(&self) -> Result<Vec<ApplicationUser>, Error> {
+        self.greenfield_get("/users").await
+    }
+
+    pub async fn get_user(&self, id_or_email: &str) -> Result<ApplicationUser, Error> {
+        self.greenfield_get(&format!("/users/{}", encode_segment(id_or_email)))
+            .await
+    }
+
+    pub async fn delete_user(&self, id_or_email: &str) -> Result<(), Error> {
+        self.greenfield_delete(&format!("/users/{}", encode_segment(id_or_email)))
+            .await
+    }
+
+    /// Locks a user out of the server, or lets it back in
+    pub async fn lock_user(&self, id_or_email: &str, locked: bool) -> Result<(), Error> {
+        self.greenfield_post_empty(
+            &format!("/users/{}/lock", encode_segment(id_or_email)),
+            Some(&LockUserRequest { locked }),
+        )
+        .await
+    }
+
+    /// Approves a user, when the server requires new users to be approved
+    pub async fn approve_user(&self, id_or_email: &str, approved: bool) -> Result<(), Error> {
+        self.greenfield_post_empty(
+            &format!("/users/{}/approve", encode_segment(id_or_email)),
+            Some(&ApproveUserRequest { approved }),
+        )
+        .await
+    }
+
+    pub async fn get_store_users(&self, store_id: &str) -> Result<Vec<StoreUser>, Error> {
+        self.greenfield_get(&format!("/stores/{}/users", encode_segment(store_id)))
+            .await
+    }
+
+    /// Gives a user access to a store with `role`
+    pub async fn add_store_user(
+        &self,
+        store_id: &str,
+        user_id_or_email: &str,
+        role: StoreRole,
+    ) -> Result<(), Error> {
+        self.greenfield_post_empty(
+            &format!("/stores/{}/users", encode_segment(store_id)),
+            Some(&StoreUserRequest {
+                user_id: user_id_or_email,
+                role,
+            }),
+        )
+        .await
+    }
+
+    pub async fn remove_store_user(
+        &self,
+        store_id: &str,
+        user_id_or_email: &str,
+    ) -> Result<(), Error> {
+        self.greenfield_delete(&format!(
+            "/stores/{}/users/{}",
+            encode_segment(store_id),
+            encode_segment(user_id_or_email)
+        ))
+        .await
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct LockUserRequest {
+    locked: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct ApproveUserRequest {
+    approved: bool,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct StoreUserRequest<'a> {
+    user_id: &'a str,
+    role: StoreRole,
+}
+
+#[cfg(test)]
+mod test {
+    use mockito::Server;
+
+    use super::*;
+    use crate::core::greenfield::test::{json_body, mock_endpoint, test_client};
+    use crate::models::user::test::USER_JSON;
+
+    #[tokio::test]
+    async fn test_create_user() {
+        let mut server = Server::new_async().await;
+        let mock = mock_endpoint(&mut server, "POST", "/users")
+            .match_body(json_body(
+                r#"{"email":"merchant@example.com","password":"correct horse","isAdministrator":true}"#,
+            ))
+            .with_status(201)
+            .with_body(USER_JSON)
+            .create_async()
+            .await;
+
+        let user = test_client(&server.url())
+            .create_user(&CreateUserRequest {
+                is_administrator: true,
+                ..CreateUserRequest::new("merchant@example.com", "correct horse")
+            })
+            .await
+            .unwrap();
+        assert!(user.is_admin());
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_add_store_user() {
+        let mut server = Server::new_async().await;
+        let mock = mock_endpoint(&mut server, "POST", "/stores/store/users")
+            .match_body(json_body(
+                r#"{"userId":"merchant@example.com","role":"Employee"}"#,
+            ))
+            .create_async()
+            .await;
+
+        test_client(&server.url())
+            .add_store_user("store", "merchant@example.com", StoreRole::Employee)
+            .await
+            .unwrap();
+        mock.assert_async().await;
+    }
+
+    const EMAIL: &str = "merchant+shop@example.com";
+    const ENCODED_EMAIL: &str = "merchant%2Bshop%40example.com";
+
+    #[tokio::test]
+    async fn test_get_and_delete_user() {
+        let mut server = Server::new_async().await;
+        let path = format!("/users/{}", ENCODED_EMAIL);
+        let get = mock_endpoint(&mut server, "GET", &path)
+            .with_body(USER_JSON)
+            .create_async()
+            .await;
+        let delete = mock_endpoint(&mut server, "DELETE", &path)
+            .create_async()
+            .await;
+
+        let client = test_client(&server.url());
+        assert!(!client.get_user(EMAIL).await.unwrap().disabled);
+        client.delete_user(EMAIL).await.unwrap();
+        get.assert_async().await;
+        delete.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_lock_and_approve_user() {
+        let mut server = Server::new_async().await;
+        let lock = mock_endpoint(
+            &mut server,
+            "POST",
+            &format!("/users/{}/lock", ENCODED_EMAIL),
+        )
+        .match_body(json_body(r#"{"locked":true}"#))
+        .create_async()
+        .await;
+        let approve = mock_endpoint(
+            &mut server,
+            "POST",
+            &format!("/users/{}/approve", ENCODED_EMAIL),
+        )
+        .match_body(json_body(r#"{"approved":false}"#))
+        .create_async()
+        .await;
+
+        let client = test_client(&server.url());
+        client.lock_user(EMAIL, true).await.unwrap();
+        client.approve_user(EMAIL, false).await.unwrap();
+        lock.assert_async().await;
+        approve.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_remove_store_user() {
+        let mut server = Server::new_async().await;
+        let mock = mock_endpoint(
+            &mut server,
+            "DELETE",
+            &format!("/stores/store/users/{}", ENCODED_EMAIL),
+        )
+        .create_async()
+        .await;
+
+        test_client(&server.url())
+            .remove_store_user("store", EMAIL)
+            .await
+            .unwrap();
+        mock.assert_async().await;
+    }
+}
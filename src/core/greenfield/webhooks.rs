@@ -0,0 +1,215 @@
+use std::collections::HashMap;
+
+use reqwest::Method;
+
+use super::encode_segment;
+use crate::core::client::{BTCPayClient, Error};
+use crate::models::*;
+
+impl BTCPayClient {
+    /// Creates a webhook. The returned one carries the secret used to sign its deliveries.
+    pub async fn create_webhook(
+        &self,
+        store_id: &str,
+        request: &WebhookRequest,
+    ) -> Result<Webhook, Error> {
+        self.greenfield_post(
+            &format!("/stores/{}/webhooks", encode_segment(store_id)),
+            request,
+        )
+        .await
+    }
+
+    pub async fn get_webhooks(&self, store_id: &str) -> Result<Vec<Webhook>, Error> {
+        self.greenfield_get(&format!("/stores/{}/webhooks", encode_segment(store_id)))
+            .await
+    }
+
+    pub async fn get_webhook(&self, store_id: &str, webhook_id: &str) -> Result<Webhook, Error> {
+        self.greenfield_get(&format!(
+            "/stores/{}/webhooks/{}",
+            encode_segment(store_id),
+            encode_segment(webhook_id)
+        ))
+        .await
+    }
+
+    pub async fn update_webhook(
+        &self,
+        store_id: &str,
+        webhook_id: &str,
+        request: &WebhookRequest,
+    ) -> Result<Webhook, Error> {
+        self.greenfield_put(
+            &format!(
+                "/stores/{}/webhooks/{}",
+                encode_segment(store_id),
+                encode_segment(webhook_id)
+            ),
+            request,
+        )
+        .await
+    }
+
+    pub async fn delete_webhook(&self, store_id: &str, webhook_id: &str) -> Result<(), Error> {
+        self.greenfield_delete(&format!(
+            "/stores/{}/webhooks/{}",
+            encode_segment(store_id),
+            encode_segment(webhook_id)
+        ))
+        .await
+    }
+
+    /// Latest deliveries of a webhook, most recent first. `count` defaults to 50 on the server.
+    pub async fn get_webhook_deliveries(
+        &self,
+        store_id: &str,
+        webhook_id: &str,
+        count: Option<usize>,
+    ) -> Result<Vec<WebhookDelivery>, Error> {
+        let mut query = HashMap::new();
+        if let Some(count) = count {
+            query.insert("count", count);
+        }
+
+        self.greenfield_get_query(
+            &format!(
+                "/stores/{}/webhooks/{}/deliveries",
+                encode_segment(store_id),
+                encode_segment(webhook_id)
+            ),
+            &query,
+        )
+        .await
+    }
+
+    pub async fn get_webhook_delivery(
+        &self,
+        store_id: &str,
+        webhook_id: &str,
+        delivery_id: &str,
+    ) -> Result<WebhookDelivery, Error> {
+        self.greenfield_get(&format!(
+            "/stores/{}/webhooks/{}/deliveries/{}",
+            encode_segment(store_id),
+            encode_segment(webhook_id),
+            encode_segment(delivery_id)
+        ))
+        .await
+    }
+
+    /// Payload that was sent with a delivery
+    pub async fn get_webhook_delivery_request(
+        &self,
+        store_id: &str,
+        webhook_id: &str,
+        delivery_id: &str,
+    ) -> Result<WebhookPayload, Error> {
+        self.greenfield_get(&format!(
+            "/stores/{}/webhooks/{}/deliveries/{}/request",
+            encode_segment(store_id),
+            encode_segment(webhook_id),
+            encode_segment(delivery_id)
+        ))
+        .await
+    }
+
+    /// Sends a delivery again, returning the id of the new delivery
+    pub async fn redeliver_webhook(
+        &self,
+        store_id: &str,
+        webhook_id: &str,
+        delivery_id: &str,
+    ) -> Result<String, Error> {
+        // The endpoint takes no body, not even a JSON `null`
+        let request = self.greenfield_request(
+            Method::POST,
+            &format!(
+                "/stores/{}/webhooks/{}/deliveries/{}/redeliver",
+                encode_segment(store_id),
+                encode_segment(webhook_id),
+                encode_segment(delivery_id)
+            ),
+        )?;
+
+        Ok(self.greenfield_send(request, false).await?.json().await?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use mockito::{Matcher, Server};
+
+    use super::*;
+    use crate::core::greenfield::test::{json_body, mock_endpoint, test_client};
+
+    #[tokio::test]
+    async fn test_create_webhook() {
+        let mut server = Server::new_async().await;
+        let mock = mock_endpoint(&mut server, "POST", "/stores/store/webhooks")
+            .match_body(json_body(
+                r#"{"url":"https://shop.example.com/btcpay","enabled":true,"automaticRedelivery":true,"authorizedEvents":{"everything":false,"specificEvents":["InvoiceSettled"]}}"#,
+            ))
+            .with_body(
+                r#"{"id":"2YJ4iWgS2Ws3ioXxJXbdLn","enabled":true,"automaticRedelivery":true,"url":"https://shop.example.com/btcpay","authorizedEvents":{"everything":false,"specificEvents":["InvoiceSettled"]},"secret":"3SJTDjvb2A3tWFyPrTqXjD"}"#,
+            )
+            .create_async()
+            .await;
+
+        let webhook = test_client(&server.url())
+            .create_webhook(
+                "store",
+                &WebhookRequest {
+                    authorized_events: AuthorizedEvents::only(vec![
+                        WebhookEventType::InvoiceSettled,
+                    ]),
+                    ..WebhookRequest::new("https://shop.example.com/btcpay")
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(webhook.secret.as_deref(), Some("3SJTDjvb2A3tWFyPrTqXjD"));
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_get_webhook_deliveries() {
+        let mut server = Server::new_async().await;
+        let mock = mock_endpoint(&mut server, "GET", "/stores/store/webhooks/hook/deliveries")
+            .match_query("count=2")
+            .with_body(
+                r#"[{"id":"Dk2vUq4jXLzRzyyQ8Dyuds","timestamp":1689615300,"httpCode":503,"errorMessage":"Service Unavailable","status":"HttpError","pruned":false},{"id":"5pVTzHZT3wxvdk1AqDqS4w","timestamp":1689615200,"httpCode":200,"errorMessage":null,"status":"HttpSuccess","pruned":false}]"#,
+            )
+            .create_async()
+            .await;
+
+        let deliveries = test_client(&server.url())
+            .get_webhook_deliveries("store", "hook", Some(2))
+            .await
+            .unwrap();
+        assert!(deliveries[0].is_failed());
+        assert!(!deliveries[1].is_failed());
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_redeliver_webhook() {
+        let mut server = Server::new_async().await;
+        let mock = mock_endpoint(
+            &mut server,
+            "POST",
+            "/stores/store/webhooks/hook/deliveries/Dk2vUq4jXLzRzyyQ8Dyuds/redeliver",
+        )
+        .match_body(Matcher::Exact(String::new()))
+        .with_body(r#""5bMpeCmA9ph7ZRGAvEhWDD""#)
+        .create_async()
+        .await;
+
+        let delivery_id = test_client(&server.url())
+            .redeliver_webhook("store", "hook", "Dk2vUq4jXLzRzyyQ8Dyuds")
+            .await
+            .unwrap();
+        assert_eq!(delivery_id, "5bMpeCmA9ph7ZRGAvEhWDD");
+        mock.assert_async().await;
+    }
+}
@@ -1,2 +1,7 @@
+pub mod builder;
 pub mod client;
 pub mod cryptography;
+mod greenfield;
+pub mod registry;
+pub mod retry;
+pub mod signer;
@@ -0,0 +1,140 @@
+use std::collections::HashMap;
+
+use bitcoin::bip32::{ChildNumber, DerivationPath, ExtendedPrivKey};
+
+use super::client::Error;
+use super::cryptography::{Cryptography, KeyPair};
+
+/// Maps store ids to hardened child indexes under a single BIP32 master key, so that every store
+/// gets its own identity while only one seed needs to be backed up.
+#[derive(Debug, Clone)]
+pub struct KeyRegistry {
+    master: ExtendedPrivKey,
+    base_path: DerivationPath,
+    stores: HashMap<String, u32>,
+}
+
+impl KeyRegistry {
+    pub fn new(master: ExtendedPrivKey, base_path: DerivationPath) -> Self {
+        KeyRegistry {
+            master,
+            base_path,
+            stores: HashMap::new(),
+        }
+    }
+
+    pub fn with_stores(
+        master: ExtendedPrivKey,
+        base_path: DerivationPath,
+        stores: HashMap<String, u32>,
+    ) -> Self {
+        KeyRegistry {
+            master,
+            base_path,
+            stores,
+        }
+    }
+
+    /// Assigns `index` to `store_id`, returning the index it was previously mapped to
+    pub fn register(&mut self, store_id: &str, index: u32) -> Result<Option<u32>, Error> {
+        if let Some((other, _)) = self
+            .stores
+            .iter()
+            .find(|(id, i)| **i == index && id.as_str() != store_id)
+        {
+            return Err(Error::DerivationIndexInUse(index, other.clone()));
+        }
+        ChildNumber::from_hardened_idx(index)?;
+
+        Ok(self.stores.insert(store_id.to_string(), index))
+    }
+
+    /// Assigns the first unused index to `store_id`, or returns the existing one
+    pub fn register_next(&mut self, store_id: &str) -> Result<u32, Error> {
+        if let Some(index) = self.stores.get(store_id) {
+            return Ok(*index);
+        }
+
+        let index = self.stores.values().max().map(|i| i + 1).unwrap_or(0);
+        self.register(store_id, index)?;
+
+        Ok(index)
+    }
+
+    pub fn unregister(&mut self, store_id: &str) -> Option<u32> {
+        self.stores.remove(store_id)
+    }
+
+    pub fn index_of(&self, store_id: &str) -> Option<u32> {
+        self.stores.get(store_id).copied()
+    }
+
+    pub fn stores(&self) -> &HashMap<String, u32> {
+        &self.stores
+    }
+
+    pub fn derivation_path(&self, store_id: &str) -> Result<DerivationPath, Error> {
+        let index = self
+            .index_of(store_id)
+            .ok_or_else(|| Error::UnknownStore(store_id.to_string()))?;
+
+        Ok(self.base_path.child(ChildNumber::from_hardened_idx(index)?))
+    }
+
+    pub fn keypair(&self, store_id: &str) -> Result<KeyPair, Error> {
+        Ok(Cryptography::derive_keypair(
+            &self.master,
+            &self.derivation_path(store_id)?,
+        )?)
+    }
+
+    pub fn sin(&self, store_id: &str) -> Result<String, Error> {
+        Ok(Cryptography::get_sin_from_key(
+            &self.keypair(store_id)?.public,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn get_test_registry() -> KeyRegistry {
+        let master = ExtendedPrivKey::new_master(bitcoin::Network::Bitcoin, &[0x42; 32]).unwrap();
+
+        KeyRegistry::new(master, "m/1000'".parse().unwrap())
+    }
+
+    #[test]
+    fn test_registry() {
+        let mut registry = get_test_registry();
+        assert_eq!(registry.register_next("store-a").unwrap(), 0);
+        assert_eq!(registry.register_next("store-b").unwrap(), 1);
+        assert_eq!(registry.register_next("store-a").unwrap(), 0);
+
+        assert_eq!(
+            registry.derivation_path("store-b").unwrap().to_string(),
+            "m/1000'/1'"
+        );
+        assert_ne!(
+            registry.sin("store-a").unwrap(),
+            registry.sin("store-b").unwrap()
+        );
+
+        let mut restored = get_test_registry();
+        restored.register("store-a", 0).unwrap();
+        assert_eq!(
+            registry.sin("store-a").unwrap(),
+            restored.sin("store-a").unwrap()
+        );
+
+        assert!(matches!(
+            registry.register("store-c", 1),
+            Err(Error::DerivationIndexInUse(1, _))
+        ));
+        assert!(matches!(
+            registry.keypair("store-c"),
+            Err(Error::UnknownStore(_))
+        ));
+    }
+}
@@ -0,0 +1,86 @@
+use std::time::Duration;
+
+use reqwest::{Response, StatusCode};
+
+use super::client::Error;
+
+/// Controls how many times, and how far apart, failed requests are retried.
+///
+/// A request is retried when the connection fails, when it times out, or when the server answers
+/// with one of the statuses in `retry_on`.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub max_attempts: usize,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    pub multiplier: u32,
+    pub retry_on: Vec<StatusCode>,
+}
+
+impl RetryPolicy {
+    /// Policy that sends every request exactly once
+    pub fn none() -> Self {
+        RetryPolicy {
+            max_attempts: 1,
+            ..Default::default()
+        }
+    }
+
+    /// Delay to wait after the failed attempt number `attempt`, starting from one
+    pub fn backoff(&self, attempt: usize) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(u32::MAX as usize) as u32;
+        let factor = self.multiplier.saturating_pow(exponent);
+
+        self.initial_backoff
+            .checked_mul(factor)
+            .unwrap_or(self.max_backoff)
+            .min(self.max_backoff)
+    }
+
+    pub(crate) fn should_retry(&self, result: &Result<Response, Error>, attempt: usize) -> bool {
+        if attempt >= self.max_attempts {
+            return false;
+        }
+
+        match result {
+            Ok(response) => self.retry_on.contains(&response.status()),
+            Err(Error::Request(e)) => e.is_connect() || e.is_timeout(),
+            Err(_) => false,
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(10),
+            multiplier: 2,
+            retry_on: vec![
+                StatusCode::BAD_GATEWAY,
+                StatusCode::SERVICE_UNAVAILABLE,
+                StatusCode::GATEWAY_TIMEOUT,
+            ],
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_backoff() {
+        let policy = RetryPolicy {
+            max_backoff: Duration::from_secs(3),
+            ..Default::default()
+        };
+
+        assert_eq!(policy.backoff(1), Duration::from_millis(500));
+        assert_eq!(policy.backoff(2), Duration::from_millis(1000));
+        assert_eq!(policy.backoff(3), Duration::from_millis(2000));
+        assert_eq!(policy.backoff(4), Duration::from_secs(3));
+        assert_eq!(policy.backoff(100), Duration::from_secs(3));
+    }
+}
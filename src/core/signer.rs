@@ -0,0 +1,212 @@
+use std::fmt::Debug;
+
+use async_trait::async_trait;
+
+use bitcoin::secp256k1::{ecdsa::Signature, PublicKey};
+
+use super::client::Error;
+use super::cryptography::{Cryptography, KeyPair};
+
+/// Source of the identity used to sign requests to BTCPay.
+///
+/// The signature must be an ECDSA signature over the SHA256 of `data`, which is the full request
+/// URL followed by the query string or body.
+#[async_trait]
+pub trait Signer: Debug + Send + Sync {
+    fn public_key(&self) -> PublicKey;
+
+    async fn sign(&self, data: &[u8]) -> Result<Signature, Error>;
+}
+
+#[async_trait]
+impl Signer for KeyPair {
+    fn public_key(&self) -> PublicKey {
+        self.public
+    }
+
+    async fn sign(&self, data: &[u8]) -> Result<Signature, Error> {
+        Ok(Cryptography::sign(data, self.secret())?.to_signature()?)
+    }
+}
+
+#[cfg(unix)]
+pub use self::daemon::{serve, SocketSigner};
+
+#[cfg(unix)]
+mod daemon {
+    use std::path::{Path, PathBuf};
+
+    use serde::{Deserialize, Serialize};
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::{UnixListener, UnixStream};
+
+    use super::*;
+    use crate::FromHex;
+
+    #[derive(Debug, Serialize, Deserialize)]
+    #[serde(tag = "method", rename_all = "camelCase")]
+    enum Request {
+        PublicKey,
+        Sign { data: String },
+    }
+
+    #[derive(Debug, Serialize, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    enum Response {
+        Result(String),
+        Error(String),
+    }
+
+    /// Signer that forwards every request to a signing daemon listening on a Unix socket.
+    ///
+    /// The protocol is one JSON request per connection, terminated by a newline, answered by a
+    /// single JSON line. See [`serve`] for the daemon side.
+    #[derive(Debug, Clone)]
+    pub struct SocketSigner {
+        path: PathBuf,
+        public_key: PublicKey,
+    }
+
+    impl SocketSigner {
+        pub async fn connect<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+            let path = path.as_ref().to_path_buf();
+            let public_key = call(&path, &Request::PublicKey)
+                .await?
+                .parse()
+                .map_err(|_| Error::InvalidResponse)?;
+
+            Ok(SocketSigner { path, public_key })
+        }
+    }
+
+    #[async_trait]
+    impl Signer for SocketSigner {
+        fn public_key(&self) -> PublicKey {
+            self.public_key
+        }
+
+        async fn sign(&self, data: &[u8]) -> Result<Signature, Error> {
+            let request = Request::Sign {
+                data: data.iter().map(|b| format!("{:02x}", b)).collect(),
+            };
+
+            call(&self.path, &request)
+                .await?
+                .parse()
+                .map_err(|_| Error::InvalidResponse)
+        }
+    }
+
+    async fn call(path: &Path, request: &Request) -> Result<String, Error> {
+        let mut stream = BufReader::new(UnixStream::connect(path).await?);
+
+        let mut line = serde_json::to_string(request)?;
+        line.push('\n');
+        stream.get_mut().write_all(line.as_bytes()).await?;
+
+        line.clear();
+        stream.read_line(&mut line).await?;
+
+        match serde_json::from_str(&line)? {
+            Response::Result(result) => Ok(result),
+            Response::Error(error) => Err(Error::Signer(error)),
+        }
+    }
+
+    /// Runs a signing daemon for `signer` on `listener`, answering requests from [`SocketSigner`]
+    /// until accepting a new connection fails.
+    pub async fn serve<S: Signer + 'static>(
+        listener: UnixListener,
+        signer: S,
+    ) -> Result<(), Error> {
+        let signer = std::sync::Arc::new(signer);
+
+        loop {
+            let (stream, _) = listener.accept().await?;
+            let signer = signer.clone();
+
+            tokio::spawn(async move {
+                let _ = handle(stream, signer.as_ref()).await;
+            });
+        }
+    }
+
+    async fn handle<S: Signer>(stream: UnixStream, signer: &S) -> Result<(), Error> {
+        let mut stream = BufReader::new(stream);
+
+        let mut line = String::new();
+        stream.read_line(&mut line).await?;
+
+        let response = match serde_json::from_str(&line) {
+            Ok(Request::PublicKey) => Response::Result(signer.public_key().to_string()),
+            Ok(Request::Sign { data }) => match Vec::<u8>::from_hex(&data) {
+                Ok(data) => match signer.sign(&data).await {
+                    Ok(signature) => Response::Result(signature.to_string()),
+                    Err(e) => Response::Error(e.to_string()),
+                },
+                Err(e) => Response::Error(e.to_string()),
+            },
+            Err(e) => Response::Error(e.to_string()),
+        };
+
+        let mut line = serde_json::to_string(&response)?;
+        line.push('\n');
+        stream.get_mut().write_all(line.as_bytes()).await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bitcoin::hashes::hex::FromHex;
+    use bitcoin::hashes::sha256::Hash as Sha256Hash;
+    use bitcoin::hashes::Hash;
+    use bitcoin::secp256k1::{Message, Secp256k1, SecretKey};
+
+    const MY_PRIVATE_KEY: &str = "31eb31ecf1a640cd91e0a1105501f36235f8c7d51d67dcf74ccc968d74cb6b25";
+
+    fn get_test_keypair() -> KeyPair {
+        SecretKey::from_slice(&Vec::<u8>::from_hex(MY_PRIVATE_KEY).unwrap())
+            .unwrap()
+            .into()
+    }
+
+    fn verify(signer: &dyn Signer, data: &[u8], signature: &Signature) {
+        let hashed = Sha256Hash::hash(data).to_byte_array();
+        Secp256k1::verification_only()
+            .verify_ecdsa(
+                &Message::from_slice(&hashed).unwrap(),
+                signature,
+                &signer.public_key(),
+            )
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_keypair_signer() {
+        let keypair = get_test_keypair();
+        let signature = keypair.sign(b"hello").await.unwrap();
+
+        verify(&keypair, b"hello", &signature);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_socket_signer() {
+        let path = std::env::temp_dir().join(format!("btcpay-signer-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let listener = tokio::net::UnixListener::bind(&path).unwrap();
+        tokio::spawn(serve(listener, get_test_keypair()));
+
+        let signer = SocketSigner::connect(&path).await.unwrap();
+        assert_eq!(signer.public_key(), get_test_keypair().public);
+
+        let signature = signer.sign(b"hello").await.unwrap();
+        verify(&signer, b"hello", &signature);
+
+        let _ = std::fs::remove_file(&path);
+    }
+}
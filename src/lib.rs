@@ -1,9 +1,20 @@
 pub mod core;
 pub mod models;
+#[cfg(feature = "qr")]
+pub mod qr;
+#[cfg(feature = "verify")]
+pub mod verify;
+pub mod webhook;
 
 pub use bitcoin::hashes::hex::FromHex;
 pub use bitcoin::secp256k1::SecretKey;
+pub use bitcoin::Network;
+pub use rust_decimal::Decimal;
 
+pub use self::core::builder::BTCPayClientBuilder;
 pub use self::core::client::BTCPayClient;
 pub use self::core::cryptography::KeyPair;
+pub use self::core::registry::KeyRegistry;
+pub use self::core::retry::RetryPolicy;
+pub use self::core::signer::Signer;
 pub use self::models::*;
@@ -0,0 +1,373 @@
+use std::collections::HashMap;
+
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use super::timestamp::{self, DateTime};
+
+string_enum! {
+    pub enum AppType {
+        PointOfSale => "PointOfSale",
+        Crowdfund => "Crowdfund",
+    }
+}
+
+string_enum! {
+    /// Layout of a Point-of-Sale app
+    pub enum PosViewStyle {
+        Static => "Static",
+        Cart => "Cart",
+        Light => "Light",
+        Print => "Print",
+    }
+}
+
+string_enum! {
+    pub enum ItemPriceType {
+        Fixed => "Fixed",
+        /// The buyer chooses the amount
+        Topup => "Topup",
+        /// The buyer chooses the amount, at least the item price
+        Minimum => "Minimum",
+    }
+}
+
+string_enum! {
+    /// How often the goal of a crowdfund starts over
+    pub enum ResetEvery {
+        Never => "Never",
+        Hour => "Hour",
+        Day => "Day",
+        Month => "Month",
+        Year => "Year",
+    }
+}
+
+/// Item of a Point-of-Sale app, or perk of a crowdfund
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppItem {
+    pub id: String,
+    pub title: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+    /// Not set for top-up items. Sent as a JSON number, like the items edited on the server.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "rust_decimal::serde::float_option::serialize"
+    )]
+    pub price: Option<Decimal>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub price_type: Option<ItemPriceType>,
+    /// Items left, unlimited if not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inventory: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub buy_button_text: Option<String>,
+    #[serde(default)]
+    pub disabled: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub categories: Vec<String>,
+}
+
+impl AppItem {
+    pub fn new(id: &str, title: &str, price: Decimal) -> Self {
+        AppItem {
+            id: id.to_string(),
+            title: title.to_string(),
+            price: Some(price),
+            price_type: Some(ItemPriceType::Fixed),
+            ..Default::default()
+        }
+    }
+}
+
+/// (De)serializes the items of an app. They're sent as a JSON-encoded `template` string, and
+/// received either that way or, on newer servers, as a plain array.
+mod template {
+    use serde::de::Error as _;
+    use serde::ser::Error as _;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    use super::AppItem;
+
+    pub fn serialize<S: Serializer>(
+        items: &Option<Vec<AppItem>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match items {
+            Some(items) => {
+                serializer.serialize_str(&serde_json::to_string(items).map_err(S::Error::custom)?)
+            }
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<AppItem>, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Template {
+            Items(Vec<AppItem>),
+            Encoded(String),
+        }
+
+        match Option::<Template>::deserialize(deserializer)? {
+            Some(Template::Items(items)) => Ok(items),
+            Some(Template::Encoded(s)) if s.trim().is_empty() => Ok(Vec::new()),
+            Some(Template::Encoded(s)) => serde_json::from_str(&s).map_err(D::Error::custom),
+            None => Ok(Vec::new()),
+        }
+    }
+}
+
+/// Fields shared by every kind of app
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct App {
+    pub id: String,
+    pub app_name: String,
+    pub store_id: String,
+    #[serde(with = "timestamp::seconds")]
+    pub created: DateTime,
+    pub app_type: AppType,
+    #[serde(default)]
+    pub archived: bool,
+}
+
+/// Body to create or update a Point-of-Sale app. Unset fields keep their current value, or the
+/// server default on creation.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PointOfSaleAppRequest {
+    pub app_name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_view: Option<PosViewStyle>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub currency: Option<String>,
+    #[serde(
+        rename = "template",
+        with = "template",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub items: Option<Vec<AppItem>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub show_custom_amount: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub show_discount: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enable_tips: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub custom_tip_percentages: Option<Vec<u32>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub redirect_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notification_url: Option<String>,
+}
+
+impl PointOfSaleAppRequest {
+    pub fn new(app_name: &str) -> Self {
+        PointOfSaleAppRequest {
+            app_name: app_name.to_string(),
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PointOfSaleApp {
+    #[serde(flatten)]
+    pub app: App,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub default_view: Option<PosViewStyle>,
+    pub currency: Option<String>,
+    #[serde(
+        alias = "template",
+        deserialize_with = "template::deserialize",
+        default
+    )]
+    pub items: Vec<AppItem>,
+    #[serde(default)]
+    pub show_custom_amount: bool,
+    #[serde(default)]
+    pub show_discount: bool,
+    #[serde(default)]
+    pub enable_tips: bool,
+    #[serde(default)]
+    pub custom_tip_percentages: Vec<u32>,
+
+    /// Fields not known to this version of the library
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
+}
+
+/// Body to create or update a crowdfund app. Unset fields keep their current value, or the server
+/// default on creation.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CrowdfundAppRequest {
+    pub app_name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target_currency: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target_amount: Option<f64>,
+    /// Refuse contributions once the target is reached
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enforce_target_amount: Option<bool>,
+    #[serde(
+        with = "timestamp::seconds::option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub start_date: Option<DateTime>,
+    #[serde(
+        with = "timestamp::seconds::option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub end_date: Option<DateTime>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reset_every: Option<ResetEvery>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reset_every_amount: Option<u32>,
+    #[serde(
+        rename = "perksTemplate",
+        with = "template",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub perks: Option<Vec<AppItem>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notification_url: Option<String>,
+}
+
+impl CrowdfundAppRequest {
+    pub fn new(app_name: &str) -> Self {
+        CrowdfundAppRequest {
+            app_name: app_name.to_string(),
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CrowdfundApp {
+    #[serde(flatten)]
+    pub app: App,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    #[serde(default)]
+    pub enabled: bool,
+    pub target_currency: Option<String>,
+    pub target_amount: Option<f64>,
+    #[serde(default)]
+    pub enforce_target_amount: bool,
+    #[serde(with = "timestamp::seconds::option", default)]
+    pub start_date: Option<DateTime>,
+    #[serde(with = "timestamp::seconds::option", default)]
+    pub end_date: Option<DateTime>,
+    pub reset_every: Option<ResetEvery>,
+    pub reset_every_amount: Option<u32>,
+    #[serde(
+        alias = "perksTemplate",
+        deserialize_with = "template::deserialize",
+        default
+    )]
+    pub perks: Vec<AppItem>,
+
+    /// Fields not known to this version of the library
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
+}
+
+/// Number of sales over the requested period, by day
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppSales {
+    pub sales_count: u64,
+    #[serde(default)]
+    pub series: Vec<AppSalesStat>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppSalesStat {
+    #[serde(with = "timestamp::seconds")]
+    pub date: DateTime,
+    /// Short name of the day, like `Mon`
+    pub label: String,
+    pub sales_count: u64,
+}
+
+/// Sales of a single item
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppItemStats {
+    pub item_code: String,
+    pub title: String,
+    pub sales_count: u64,
+    pub total: f64,
+    pub total_formatted: String,
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+
+    pub const POS_APP_JSON: &str = r#"{"id":"3Z2k4sQ9LrGfW6N9Tq2bkTkgYnxx","appName":"Shop","storeId":"8vZCm1j3mNy2BcUVCgTbQaGzzRd1V1tXxpuNwDLfprbi","created":1689615287,"appType":"PointOfSale","archived":false,"title":"Corner shop","description":null,"defaultView":"Cart","currency":"EUR","items":[{"id":"coffee","title":"Coffee","price":2.5,"priceType":"Fixed","inventory":40,"buyButtonText":"Order","disabled":false},{"id":"tip","title":"Tip jar","price":null,"priceType":"Topup"}],"showCustomAmount":false,"showDiscount":true,"enableTips":true,"customTipPercentages":[10,15,20],"formId":null}"#;
+
+    pub const CROWDFUND_APP_JSON: &str = r#"{"id":"4Lk9m2RrVbH8a7Zt5pWc3ZqGhJyy","appName":"Roof","storeId":"8vZCm1j3mNy2BcUVCgTbQaGzzRd1V1tXxpuNwDLfprbi","created":1689615287,"appType":"Crowdfund","archived":false,"title":"New roof","description":null,"enabled":true,"targetCurrency":"USD","targetAmount":5000.0,"enforceTargetAmount":false,"startDate":null,"endDate":1692293687,"resetEvery":"Never","resetEveryAmount":1,"perks":[{"id":"tshirt","title":"T-shirt","price":25.0,"priceType":"Fixed","disabled":false}],"formId":null}"#;
+
+    #[test]
+    fn test_parse_pos_app() {
+        let app: PointOfSaleApp = serde_json::from_str(POS_APP_JSON).unwrap();
+
+        assert_eq!(app.app.app_type, AppType::PointOfSale);
+        assert_eq!(app.default_view, Some(PosViewStyle::Cart));
+        assert_eq!(app.items[0].inventory, Some(40));
+        assert_eq!(app.items[0].buy_button_text.as_deref(), Some("Order"));
+        assert_eq!(app.items[1].price_type, Some(ItemPriceType::Topup));
+        assert!(app.extra.contains_key("formId"));
+        assert!(!app.extra.contains_key("appName"));
+
+        // Older servers send the items as an encoded template
+        let app: CrowdfundApp = serde_json::from_str(
+            r#"{"id":"a","appName":"Roof","storeId":"s","created":1689615287,"appType":"Crowdfund","targetCurrency":"USD","targetAmount":5000.0,"startDate":null,"endDate":1692293687,"perksTemplate":"[{\"id\":\"tshirt\",\"title\":\"T-shirt\",\"price\":25.0}]"}"#,
+        )
+        .unwrap();
+        assert_eq!(app.perks[0].price, Some(Decimal::new(25, 0)));
+        assert_eq!(app.start_date, None);
+        assert_eq!(timestamp::to_millis(&app.end_date.unwrap()), 1692293687000);
+    }
+
+    #[test]
+    fn test_serialize_request() {
+        let request = PointOfSaleAppRequest {
+            default_view: Some(PosViewStyle::Static),
+            items: Some(vec![AppItem {
+                inventory: Some(40),
+                ..AppItem::new("coffee", "Coffee", Decimal::new(25, 1))
+            }]),
+            ..PointOfSaleAppRequest::new("Shop")
+        };
+
+        assert_eq!(
+            serde_json::to_string(&request).unwrap(),
+            r#"{"appName":"Shop","defaultView":"Static","template":"[{\"id\":\"coffee\",\"title\":\"Coffee\",\"price\":2.5,\"priceType\":\"Fixed\",\"inventory\":40,\"disabled\":false}]"}"#
+        );
+    }
+}
@@ -1,8 +1,12 @@
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 
-use serde::{Deserialize, Serialize};
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
-use super::invoice::SupportedCurrency;
+use super::greenfield_invoice::{AdditionalStatus, GreenfieldInvoiceStatus};
+use super::invoice::{ExceptionStatus, InvoiceStatus, SupportedCurrency};
+use super::timestamp::{self, DateTime};
+use crate::core::client::Error;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -10,23 +14,222 @@ pub struct PairClientResponse {
     pub merchant: String,
 }
 
+/// Filters for [`get_invoices`](crate::BTCPayClient::get_invoices) and
+/// [`get_greenfield_invoices`](crate::BTCPayClient::get_greenfield_invoices).
+///
+/// The legacy API only filters by a single status, and ignores the fields marked as
+/// Greenfield-only: setting them makes the legacy request fail up front rather than returning
+/// unfiltered results. The Greenfield API doesn't filter by `item_code`.
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct GetInvoicesArgs {
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub status: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(
+        default,
+        skip_serializing_if = "BTreeSet::is_empty",
+        serialize_with = "serialize_status",
+        deserialize_with = "deserialize_status"
+    )]
+    pub status: BTreeSet<InvoiceStatus>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub order_id: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub item_code: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub date_start: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub date_end: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_timestamp",
+        deserialize_with = "deserialize_timestamp"
+    )]
+    pub date_start: Option<DateTime>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_timestamp",
+        deserialize_with = "deserialize_timestamp"
+    )]
+    pub date_end: Option<DateTime>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub limit: Option<usize>,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub offset: Option<usize>,
+
+    /// Greenfield-only
+    #[serde(default, skip_serializing)]
+    pub text_search: Option<String>,
+    /// Greenfield-only, required by it
+    #[serde(default, skip_serializing)]
+    pub store_id: Option<String>,
+    /// Greenfield-only
+    #[serde(default, skip_serializing)]
+    pub exception_status: Option<ExceptionStatus>,
+    /// Greenfield-only
+    #[serde(default, skip_serializing)]
+    pub include_archived: Option<bool>,
+}
+
+impl GetInvoicesArgs {
+    /// Checks the filters supported by both APIs
+    pub fn validate(&self) -> Result<(), Error> {
+        if let (Some(start), Some(end)) = (&self.date_start, &self.date_end) {
+            if timestamp::to_millis(start) > timestamp::to_millis(end) {
+                return Err(Error::InvalidFilter("date_start is after date_end".into()));
+            }
+        }
+        if self.limit == Some(0) {
+            return Err(Error::InvalidFilter("limit must be positive".into()));
+        }
+
+        Ok(())
+    }
+
+    /// Same as [`validate`](Self::validate), also rejecting what the legacy API doesn't support
+    pub fn validate_legacy(&self) -> Result<(), Error> {
+        self.validate()?;
+
+        if self.status.len() > 1 {
+            return Err(Error::InvalidFilter(
+                "the legacy API only filters by a single status".into(),
+            ));
+        }
+
+        let greenfield_only = [
+            ("text_search", self.text_search.is_some()),
+            ("store_id", self.store_id.is_some()),
+            ("exception_status", self.exception_status.is_some()),
+            ("include_archived", self.include_archived.is_some()),
+        ];
+        if let Some((name, _)) = greenfield_only.iter().find(|(_, set)| *set) {
+            return Err(Error::InvalidFilter(format!(
+                "{} is only supported by the Greenfield API",
+                name
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Query string of the Greenfield request. Statuses are mapped to their Greenfield equivalent,
+    /// the way the server does it for legacy invoices.
+    pub(crate) fn greenfield_query(&self) -> Result<Vec<(&'static str, String)>, Error> {
+        self.validate()?;
+
+        if self.store_id.is_none() {
+            return Err(Error::InvalidFilter(
+                "store_id is required by the Greenfield API".into(),
+            ));
+        }
+        if self.item_code.is_some() {
+            return Err(Error::InvalidFilter(
+                "item_code is only supported by the legacy API".into(),
+            ));
+        }
+
+        let mut query = Vec::new();
+        if let Some(order_id) = &self.order_id {
+            query.push(("orderId", order_id.clone()));
+        }
+        if let Some(text_search) = &self.text_search {
+            query.push(("textSearch", text_search.clone()));
+        }
+        let statuses: BTreeSet<_> = self.status.iter().map(greenfield_status).collect();
+        query.extend(statuses.into_iter().map(|s| ("status", s.to_string())));
+        if let Some(exception_status) = &self.exception_status {
+            query.push((
+                "exceptionStatus",
+                greenfield_exception_status(exception_status),
+            ));
+        }
+        if let Some(include_archived) = self.include_archived {
+            query.push(("includeArchived", include_archived.to_string()));
+        }
+        if let Some(start) = &self.date_start {
+            query.push((
+                "startDate",
+                timestamp::to_millis(start).div_euclid(1000).to_string(),
+            ));
+        }
+        if let Some(end) = &self.date_end {
+            query.push((
+                "endDate",
+                timestamp::to_millis(end).div_euclid(1000).to_string(),
+            ));
+        }
+        if let Some(offset) = self.offset {
+            query.push(("skip", offset.to_string()));
+        }
+        if let Some(limit) = self.limit {
+            query.push(("take", limit.to_string()));
+        }
+
+        Ok(query)
+    }
+}
+
+fn greenfield_status(status: &InvoiceStatus) -> GreenfieldInvoiceStatus {
+    match status {
+        InvoiceStatus::New => GreenfieldInvoiceStatus::New,
+        InvoiceStatus::Paid => GreenfieldInvoiceStatus::Processing,
+        InvoiceStatus::Confirmed | InvoiceStatus::Completed => GreenfieldInvoiceStatus::Settled,
+        InvoiceStatus::Expired => GreenfieldInvoiceStatus::Expired,
+        InvoiceStatus::Invalid => GreenfieldInvoiceStatus::Invalid,
+        InvoiceStatus::Unknown(s) => GreenfieldInvoiceStatus::Unknown(s.clone()),
+    }
+}
+
+fn greenfield_exception_status(status: &ExceptionStatus) -> String {
+    match status {
+        ExceptionStatus::None => AdditionalStatus::None,
+        ExceptionStatus::PaidPartial => AdditionalStatus::PaidPartial,
+        ExceptionStatus::PaidOver => AdditionalStatus::PaidOver,
+        ExceptionStatus::PaidLate => AdditionalStatus::PaidLate,
+        ExceptionStatus::Marked => AdditionalStatus::Marked,
+        ExceptionStatus::Unknown(s) => AdditionalStatus::Unknown(s.clone()),
+    }
+    .to_string()
+}
+
+fn serialize_status<S: Serializer>(
+    status: &BTreeSet<InvoiceStatus>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(
+        &status
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>()
+            .join(","),
+    )
+}
+
+fn deserialize_status<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<BTreeSet<InvoiceStatus>, D::Error> {
+    Ok(String::deserialize(deserializer)?
+        .split(',')
+        .filter(|s| !s.is_empty())
+        .map(InvoiceStatus::from)
+        .collect())
+}
+
+// The server binds these to a `DateTimeOffset`, so they are sent as RFC 3339 in UTC
+fn serialize_timestamp<S: Serializer>(
+    timestamp: &Option<DateTime>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(&timestamp::to_rfc3339(
+        timestamp.as_ref().expect("skipped when none"),
+    ))
+}
+
+fn deserialize_timestamp<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<DateTime>, D::Error> {
+    Option::<String>::deserialize(deserializer)?
+        .map(|s| {
+            timestamp::from_rfc3339(&s)
+                .ok_or_else(|| D::Error::custom(format!("invalid timestamp: {}", s)))
+        })
+        .transpose()
 }
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
@@ -105,11 +308,124 @@ pub enum OrderId {
     Number(usize),
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(rename_all = "kebab-case")]
-pub enum TransactionSpeed {
-    Low,
-    LowMedium,
-    Medium,
-    High,
+impl std::fmt::Display for OrderId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OrderId::String(s) => write!(f, "{}", s),
+            OrderId::Number(n) => write!(f, "{}", n),
+        }
+    }
+}
+
+string_enum! {
+    pub enum TransactionSpeed {
+        Low => "low",
+        LowMedium => "low-medium",
+        Medium => "medium",
+        High => "high",
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::models::timestamp::from_millis;
+
+    #[test]
+    fn test_serialize_get_invoices_args() {
+        let args = GetInvoicesArgs {
+            status: vec![InvoiceStatus::Paid].into_iter().collect(),
+            date_start: Some(from_millis(1587907315000)),
+            limit: Some(10),
+            ..Default::default()
+        };
+        args.validate_legacy().unwrap();
+
+        let query = serde_urlencoded::to_string(&args).unwrap();
+        assert_eq!(
+            query,
+            "status=paid&dateStart=2020-04-26T13%3A21%3A55Z&limit=10"
+        );
+
+        let parsed: GetInvoicesArgs = serde_urlencoded::from_str(&query).unwrap();
+        assert_eq!(serde_urlencoded::to_string(&parsed).unwrap(), query);
+    }
+
+    #[test]
+    fn test_greenfield_query() {
+        let args = GetInvoicesArgs {
+            status: vec![
+                InvoiceStatus::Paid,
+                InvoiceStatus::Confirmed,
+                InvoiceStatus::Completed,
+            ]
+            .into_iter()
+            .collect(),
+            text_search: Some("coffee".into()),
+            store_id: Some("store".into()),
+            exception_status: Some(ExceptionStatus::PaidPartial),
+            include_archived: Some(true),
+            date_start: Some(from_millis(1587907315000)),
+            date_end: Some(from_millis(1587910915999)),
+            limit: Some(10),
+            offset: Some(20),
+            ..Default::default()
+        };
+        let query = args.greenfield_query().unwrap();
+
+        assert_eq!(
+            serde_urlencoded::to_string(&query).unwrap(),
+            "textSearch=coffee&status=Processing&status=Settled&exceptionStatus=PaidPartial\
+             &includeArchived=true&startDate=1587907315&endDate=1587910915&skip=20&take=10"
+        );
+        assert!(matches!(
+            args.validate_legacy(),
+            Err(Error::InvalidFilter(_))
+        ));
+    }
+
+    #[test]
+    fn test_validate_get_invoices_args() {
+        let args = GetInvoicesArgs {
+            status: vec![InvoiceStatus::Paid, InvoiceStatus::Confirmed]
+                .into_iter()
+                .collect(),
+            ..Default::default()
+        };
+        assert!(matches!(
+            args.validate_legacy(),
+            Err(Error::InvalidFilter(_))
+        ));
+        args.validate().unwrap();
+
+        let args = GetInvoicesArgs {
+            date_start: Some(from_millis(2000)),
+            date_end: Some(from_millis(1000)),
+            ..Default::default()
+        };
+        assert!(matches!(args.validate(), Err(Error::InvalidFilter(_))));
+
+        let args = GetInvoicesArgs {
+            store_id: Some("store".into()),
+            ..Default::default()
+        };
+        assert!(matches!(
+            args.validate_legacy(),
+            Err(Error::InvalidFilter(_))
+        ));
+
+        let args = GetInvoicesArgs {
+            item_code: Some("coffee".into()),
+            ..Default::default()
+        };
+        assert!(matches!(
+            args.greenfield_query(),
+            Err(Error::InvalidFilter(_))
+        ));
+        let args = GetInvoicesArgs::default();
+        assert!(matches!(
+            args.greenfield_query(),
+            Err(Error::InvalidFilter(_))
+        ));
+    }
 }
@@ -0,0 +1,321 @@
+use std::collections::HashMap;
+
+use rust_decimal::Decimal;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize, Serializer};
+
+use super::payment_method::PaymentMethodId;
+use super::timestamp::{self, DateTime};
+use crate::core::client::Error;
+
+string_enum! {
+    pub enum GreenfieldInvoiceStatus {
+        New => "New",
+        Processing => "Processing",
+        Expired => "Expired",
+        Invalid => "Invalid",
+        Settled => "Settled",
+    }
+}
+
+/// Status an invoice can be manually marked as, see
+/// [`mark_invoice_status`](crate::BTCPayClient::mark_invoice_status)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MarkInvoiceStatus {
+    Settled,
+    Invalid,
+}
+
+string_enum! {
+    pub enum AdditionalStatus {
+        None => "None",
+        PaidLate => "PaidLate",
+        PaidPartial => "PaidPartial",
+        Marked => "Marked",
+        Invalid => "Invalid",
+        PaidOver => "PaidOver",
+    }
+}
+
+string_enum! {
+    pub enum InvoiceType {
+        Standard => "Standard",
+        /// Invoice without an amount, settled by whatever the buyer pays
+        TopUp => "TopUp",
+    }
+}
+
+string_enum! {
+    /// Confirmations required before an invoice is settled
+    pub enum SpeedPolicy {
+        HighSpeed => "HighSpeed",
+        MediumSpeed => "MediumSpeed",
+        LowMediumSpeed => "LowMediumSpeed",
+        LowSpeed => "LowSpeed",
+    }
+}
+
+/// Checkout options of a Greenfield invoice. Unset fields fall back to the store settings.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CheckoutOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub speed_policy: Option<SpeedPolicy>,
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_payment_methods"
+    )]
+    pub payment_methods: Option<Vec<PaymentMethodId>>,
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_payment_method"
+    )]
+    pub default_payment_method: Option<PaymentMethodId>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expiration_minutes: Option<u32>,
+    /// Minutes after the expiration during which late payments are still detected
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub monitoring_minutes: Option<u32>,
+    /// Percentage of the amount that can be missing for the invoice to count as paid
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub payment_tolerance: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none", rename = "redirectURL")]
+    pub redirect_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub redirect_automatically: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub requires_refund_email: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_language: Option<String>,
+}
+
+// The server only understands Greenfield ids in requests, while responses of older servers may
+// use the legacy ones
+fn serialize_payment_methods<S: Serializer>(
+    payment_methods: &Option<Vec<PaymentMethodId>>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match payment_methods {
+        Some(payment_methods) => serializer.collect_seq(
+            payment_methods
+                .iter()
+                .map(PaymentMethodId::to_greenfield_id),
+        ),
+        None => serializer.serialize_none(),
+    }
+}
+
+fn serialize_payment_method<S: Serializer>(
+    payment_method: &Option<PaymentMethodId>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match payment_method {
+        Some(payment_method) => serializer.serialize_str(&payment_method.to_greenfield_id()),
+        None => serializer.serialize_none(),
+    }
+}
+
+/// Metadata fields understood by the server. Any other field can be added to `extra`, or a custom
+/// type can be used as metadata instead.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InvoiceMetadata {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub order_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub order_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub item_desc: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub item_code: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub buyer_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub buyer_email: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub buyer_country: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub buyer_zip: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub buyer_state: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub buyer_city: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub buyer_address1: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub buyer_address2: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub buyer_phone: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pos_data: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tax_included: Option<f64>,
+
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
+}
+
+/// Body to create a Greenfield invoice, with metadata of any serializable type `T`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateGreenfieldInvoiceRequest<T = serde_json::Value> {
+    /// Omitted for top-up invoices. Sent as a decimal string, like the server returns it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub amount: Option<Decimal>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub currency: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<T>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub checkout: Option<CheckoutOptions>,
+}
+
+impl CreateGreenfieldInvoiceRequest {
+    pub fn new(amount: Decimal, currency: &str) -> Self {
+        CreateGreenfieldInvoiceRequest {
+            amount: Some(amount),
+            currency: Some(currency.to_string()),
+            metadata: None,
+            checkout: None,
+        }
+    }
+
+    /// Invoice without an amount, settled by whatever the buyer pays
+    pub fn top_up(currency: &str) -> Self {
+        CreateGreenfieldInvoiceRequest {
+            amount: None,
+            ..Self::new(Decimal::ZERO, currency)
+        }
+    }
+}
+
+impl<T> CreateGreenfieldInvoiceRequest<T> {
+    pub fn metadata<M: Serialize>(self, metadata: M) -> CreateGreenfieldInvoiceRequest<M> {
+        CreateGreenfieldInvoiceRequest {
+            amount: self.amount,
+            currency: self.currency,
+            metadata: Some(metadata),
+            checkout: self.checkout,
+        }
+    }
+
+    pub fn checkout(mut self, checkout: CheckoutOptions) -> Self {
+        self.checkout = Some(checkout);
+        self
+    }
+}
+
+/// Invoice as returned by the Greenfield API
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GreenfieldInvoice {
+    pub id: String,
+    pub store_id: String,
+    pub amount: String,
+    pub currency: String,
+    #[serde(rename = "type")]
+    pub invoice_type: InvoiceType,
+    pub checkout_link: String,
+    pub status: GreenfieldInvoiceStatus,
+    pub additional_status: AdditionalStatus,
+    #[serde(with = "timestamp::seconds")]
+    pub created_time: DateTime,
+    #[serde(with = "timestamp::seconds")]
+    pub expiration_time: DateTime,
+    #[serde(with = "timestamp::seconds")]
+    pub monitoring_expiration: DateTime,
+    #[serde(default)]
+    pub available_statuses_for_manual_marking: Vec<GreenfieldInvoiceStatus>,
+    #[serde(default)]
+    pub archived: bool,
+    #[serde(default)]
+    pub metadata: serde_json::Value,
+    #[serde(default)]
+    pub checkout: CheckoutOptions,
+
+    /// Fields not known to this version of the library
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
+}
+
+impl GreenfieldInvoice {
+    /// Reads the metadata back as `T`, usually the type it was created with
+    pub fn metadata_as<T: DeserializeOwned>(&self) -> Result<T, Error> {
+        Ok(serde_json::from_value(self.metadata.clone())?)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+
+    pub const INVOICE_JSON: &str = r#"{"id":"PJuNNh4AZsfGbLqsLP1Ekq","storeId":"8vZCm1j3mNy2BcUVCgTbQaGzzRd1V1tXxpuNwDLfprbi","amount":"20.00","currency":"USD","type":"Standard","checkoutLink":"https://btcpay.example.com/i/PJuNNh4AZsfGbLqsLP1Ekq","status":"New","additionalStatus":"None","createdTime":1689615287,"expirationTime":1689616187,"monitoringExpiration":1689702587,"availableStatusesForManualMarking":["Settled","Invalid"],"archived":false,"metadata":{"orderId":"A-1042","lines":[{"sku":"TSHIRT","quantity":2}]},"checkout":{"speedPolicy":"MediumSpeed","paymentMethods":["BTC","BTC-LightningNetwork"],"defaultPaymentMethod":null,"expirationMinutes":15,"monitoringMinutes":1440,"paymentTolerance":0.0,"redirectURL":null,"redirectAutomatically":false,"requiresRefundEmail":null,"defaultLanguage":null},"receipt":{"enabled":null}}"#;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Line {
+        sku: String,
+        quantity: u32,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct OrderMetadata {
+        order_id: String,
+        lines: Vec<Line>,
+    }
+
+    #[test]
+    fn test_serialize_request() {
+        let request = CreateGreenfieldInvoiceRequest::new(Decimal::new(2000, 2), "USD")
+            .metadata(OrderMetadata {
+                order_id: "A-1042".into(),
+                lines: vec![Line {
+                    sku: "TSHIRT".into(),
+                    quantity: 2,
+                }],
+            })
+            .checkout(CheckoutOptions {
+                speed_policy: Some(SpeedPolicy::MediumSpeed),
+                payment_methods: Some(vec![
+                    PaymentMethodId::on_chain("BTC"),
+                    PaymentMethodId::lightning("BTC"),
+                ]),
+                default_payment_method: Some(PaymentMethodId::lightning("BTC")),
+                expiration_minutes: Some(15),
+                redirect_url: Some("https://shop.example.com/thanks".into()),
+                ..Default::default()
+            });
+
+        assert_eq!(
+            serde_json::to_string(&request).unwrap(),
+            r#"{"amount":"20.00","currency":"USD","metadata":{"orderId":"A-1042","lines":[{"sku":"TSHIRT","quantity":2}]},"checkout":{"speedPolicy":"MediumSpeed","paymentMethods":["BTC-CHAIN","BTC-LN"],"defaultPaymentMethod":"BTC-LN","expirationMinutes":15,"redirectURL":"https://shop.example.com/thanks"}}"#
+        );
+        assert_eq!(
+            serde_json::to_string(&CreateGreenfieldInvoiceRequest::top_up("USD")).unwrap(),
+            r#"{"currency":"USD"}"#
+        );
+    }
+
+    #[test]
+    fn test_parse_invoice() {
+        let invoice: GreenfieldInvoice = serde_json::from_str(INVOICE_JSON).unwrap();
+
+        assert_eq!(invoice.status, GreenfieldInvoiceStatus::New);
+        assert_eq!(
+            invoice.checkout.speed_policy,
+            Some(SpeedPolicy::MediumSpeed)
+        );
+        assert_eq!(
+            invoice.checkout.payment_methods.as_ref().unwrap()[1],
+            PaymentMethodId::lightning("BTC")
+        );
+        assert!(invoice.extra.contains_key("receipt"));
+
+        let metadata: OrderMetadata = invoice.metadata_as().unwrap();
+        assert_eq!(metadata.lines[0].quantity, 2);
+        let metadata: InvoiceMetadata = invoice.metadata_as().unwrap();
+        assert_eq!(metadata.order_id.as_deref(), Some("A-1042"));
+        assert!(metadata.extra.contains_key("lines"));
+    }
+}
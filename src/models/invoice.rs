@@ -1,7 +1,11 @@
 use std::collections::HashMap;
+use std::time::Duration;
 
 use serde::{Deserialize, Serialize};
 
+use super::payment_method::{PaymentMethodId, PaymentMethods, PaymentType};
+use super::timestamp::{self, DateTime};
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Invoice {
@@ -21,13 +25,17 @@ pub struct Invoice {
     pub order_id: Option<String>,
     pub guid: Option<String>,
     pub id: String,
-    pub invoice_time: u64,
-    pub expiration_time: u64,
-    pub current_time: u64,
+    #[serde(with = "timestamp::millis")]
+    pub invoice_time: DateTime,
+    #[serde(with = "timestamp::millis")]
+    pub expiration_time: DateTime,
+    /// Server time when the invoice was fetched
+    #[serde(with = "timestamp::millis")]
+    pub current_time: DateTime,
     pub low_fee_detected: Option<bool>,
     pub btc_paid: Option<String>,
     pub rate: f32,
-    pub exception_status: bool,
+    pub exception_status: ExceptionStatus,
     pub payment_urls: Option<PaymentUrl>,
     pub refund_address_request_pending: Option<bool>,
     pub buyer_paid_btc_miner_fee: Option<String>,
@@ -48,24 +56,142 @@ pub struct Invoice {
     pub status: InvoiceStatus,
     pub checkout_type: Option<String>,
     pub token: String,
+
+    /// Fields not known to this version of the library
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
-pub enum InvoiceStatus {
-    New,
-    Expired,
-    Paid,
-    Confirmed,
-    Completed,
-    Invalid,
+impl Invoice {
+    /// Milliseconds the server clock is ahead of the local one, given the local time the invoice
+    /// was fetched at. The expiration methods take the same `received_at` to measure the
+    /// expiration on the server clock.
+    pub fn clock_skew_millis(&self, received_at: &DateTime) -> i64 {
+        timestamp::to_millis(&self.current_time) - timestamp::to_millis(received_at)
+    }
+
+    /// Whether the invoice has expired at the local time `now`
+    pub fn is_expired_at(&self, now: &DateTime, received_at: &DateTime) -> bool {
+        self.millis_remaining_at(now, received_at) <= 0
+    }
+
+    pub fn is_underpaid(&self) -> bool {
+        self.exception_status == ExceptionStatus::PaidPartial
+    }
+
+    pub fn is_overpaid(&self) -> bool {
+        self.exception_status == ExceptionStatus::PaidOver
+    }
+
+    pub fn is_paid_late(&self) -> bool {
+        self.exception_status == ExceptionStatus::PaidLate
+    }
+
+    pub fn is_expired(&self, received_at: &DateTime) -> bool {
+        self.is_expired_at(&timestamp::now(), received_at)
+    }
+
+    /// Time left at the local time `now` before the invoice expires, or zero if it already has
+    pub fn time_remaining_at(&self, now: &DateTime, received_at: &DateTime) -> Duration {
+        let millis = self.millis_remaining_at(now, received_at);
+
+        Duration::from_millis(millis.max(0) as u64)
+    }
+
+    pub fn time_remaining(&self, received_at: &DateTime) -> Duration {
+        self.time_remaining_at(&timestamp::now(), received_at)
+    }
+
+    fn millis_remaining_at(&self, now: &DateTime, received_at: &DateTime) -> i64 {
+        let server_now = timestamp::to_millis(now) + self.clock_skew_millis(received_at);
+
+        timestamp::to_millis(&self.expiration_time) - server_now
+    }
+}
+
+string_enum! {
+    pub enum InvoiceStatus {
+        New => "new",
+        Expired => "expired",
+        Paid => "paid",
+        Confirmed => "confirmed",
+        Completed => "completed",
+        Invalid => "invalid",
+    }
+}
+
+/// Reason an invoice didn't follow the normal payment flow. The server sends `false` when there is
+/// none.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ExceptionStatus {
+    #[default]
+    None,
+    PaidPartial,
+    PaidOver,
+    PaidLate,
+    Marked,
+    /// Value not known to this version of the library
+    Unknown(String),
+}
+
+impl ExceptionStatus {
+    fn as_str(&self) -> Option<&str> {
+        match self {
+            ExceptionStatus::None => None,
+            ExceptionStatus::PaidPartial => Some("paidPartial"),
+            ExceptionStatus::PaidOver => Some("paidOver"),
+            ExceptionStatus::PaidLate => Some("paidLate"),
+            ExceptionStatus::Marked => Some("marked"),
+            ExceptionStatus::Unknown(s) => Some(s),
+        }
+    }
+}
+
+impl Serialize for ExceptionStatus {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.as_str() {
+            Some(s) => serializer.serialize_str(s),
+            None => serializer.serialize_bool(false),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for ExceptionStatus {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de::Error;
+
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Raw {
+            Bool(bool),
+            String(String),
+        }
+
+        match Raw::deserialize(deserializer)? {
+            Raw::Bool(false) => Ok(ExceptionStatus::None),
+            Raw::Bool(true) => Err(D::Error::custom("exception status can't be `true`")),
+            Raw::String(s) => match s.as_str() {
+                "paidPartial" => Ok(ExceptionStatus::PaidPartial),
+                "paidOver" => Ok(ExceptionStatus::PaidOver),
+                "paidLate" => Ok(ExceptionStatus::PaidLate),
+                "marked" => Ok(ExceptionStatus::Marked),
+                _ => Ok(ExceptionStatus::Unknown(s)),
+            },
+        }
+    }
+}
+
+impl std::fmt::Display for ExceptionStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str().unwrap_or("none"))
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct CryptoInfo {
     pub crypto_code: String,
-    pub payment_type: String,
+    pub payment_type: PaymentType,
     pub rate: f32,
     pub ex_rates: HashMap<String, f32>,
     pub paid: String,
@@ -81,17 +207,13 @@ pub struct CryptoInfo {
     pub payments: Vec<serde_json::Value>,
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
-#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
-pub struct Addresses {
-    #[serde(rename = "BTCLike")]
-    #[serde(default)]
-    pub btclike: Option<String>,
-    #[serde(default)]
-    pub btc_lnurlpay: Option<String>,
-    #[serde(rename = "BTC_LightningLike")]
-    #[serde(default)]
-    pub btc_lightning_like: Option<String>,
+/// Payment destination for each payment method: an address, a BOLT11 invoice or an LNURL
+pub type Addresses = PaymentMethods<String>;
+
+impl CryptoInfo {
+    pub fn payment_method_id(&self) -> PaymentMethodId {
+        PaymentMethodId::new(&self.crypto_code, self.payment_type.clone())
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -112,28 +234,10 @@ pub struct InvoiceFlags {
     pub refundable: bool,
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
-#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
-pub struct PaymentCodes {
-    #[serde(rename = "BTCLike")]
-    pub btclike: Option<PaymentUrl>,
-    pub btc_lnurlpay: Option<PaymentUrl>,
-    #[serde(rename = "BTC_LightningLike")]
-    pub btc_lightning_like: Option<PaymentUrl>,
-}
+pub type PaymentCodes = PaymentMethods<PaymentUrl>;
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
-#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
-pub struct Payment {
-    #[serde(rename = "BTCLike")]
-    #[serde(default)]
-    pub btclike: f32,
-    #[serde(default)]
-    pub btc_lnurlpay: f32,
-    #[serde(rename = "BTC_LightningLike")]
-    #[serde(default)]
-    pub btc_lightning_like: f32,
-}
+/// Amount for each payment method, in the smallest unit of its currency
+pub type Payment = PaymentMethods<f64>;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -163,9 +267,111 @@ pub struct SupportedCurrency {
 }
 
 #[cfg(test)]
-mod test {
+pub(crate) mod test {
     use super::*;
 
+    #[test]
+    fn test_invoice_expiration() {
+        let invoice: Invoice = serde_json::from_str(get_test_invoices_json()[0]).unwrap();
+        assert_eq!(timestamp::to_millis(&invoice.invoice_time), 1689615286000);
+
+        // Local clock one minute behind the server
+        let received_at = timestamp::from_millis(1689615287973 - 60_000);
+        assert_eq!(invoice.clock_skew_millis(&received_at), 60_000);
+
+        let expiration = 1689616186000;
+        let before = timestamp::from_millis(expiration - 60_001);
+        assert!(!invoice.is_expired_at(&before, &received_at));
+        assert_eq!(
+            invoice.time_remaining_at(&before, &received_at),
+            Duration::from_millis(1)
+        );
+        let after = timestamp::from_millis(expiration - 60_000);
+        assert!(invoice.is_expired_at(&after, &received_at));
+        assert_eq!(
+            invoice.time_remaining_at(&after, &received_at),
+            Duration::ZERO
+        );
+    }
+
+    #[test]
+    fn test_parse_exception_status() {
+        let parsed: Vec<ExceptionStatus> =
+            serde_json::from_str(r#"[false, "paidPartial", "paidOver", "paidLate", "marked"]"#)
+                .unwrap();
+        assert_eq!(
+            parsed,
+            vec![
+                ExceptionStatus::None,
+                ExceptionStatus::PaidPartial,
+                ExceptionStatus::PaidOver,
+                ExceptionStatus::PaidLate,
+                ExceptionStatus::Marked,
+            ]
+        );
+        assert_eq!(
+            serde_json::to_string(&parsed).unwrap(),
+            r#"[false,"paidPartial","paidOver","paidLate","marked"]"#
+        );
+
+        let json = get_test_invoices_json()[1].replace(
+            r#""exceptionStatus":false"#,
+            r#""exceptionStatus":"paidPartial""#,
+        );
+        let invoice: Invoice = serde_json::from_str(&json).unwrap();
+        assert!(invoice.is_underpaid());
+        assert!(!invoice.is_overpaid());
+    }
+
+    #[test]
+    fn test_parse_unknown_fields() {
+        let json = get_test_invoices_json()[1]
+            .replace(r#""status":"new""#, r#""status":"settled""#)
+            .replace(
+                r#""exceptionStatus":false"#,
+                r#""exceptionStatus":"paidWithMagic","archived":true"#,
+            );
+        let invoice: Invoice = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(invoice.status, InvoiceStatus::Unknown("settled".into()));
+        assert_eq!(
+            invoice.exception_status,
+            ExceptionStatus::Unknown("paidWithMagic".into())
+        );
+        assert_eq!(invoice.extra["archived"], serde_json::Value::Bool(true));
+
+        let reserialized = serde_json::to_value(&invoice).unwrap();
+        assert_eq!(reserialized["status"], "settled");
+        assert_eq!(reserialized["archived"], true);
+    }
+
+    #[test]
+    fn test_parse_payment_methods() {
+        let lightning: Invoice = serde_json::from_str(get_test_invoices_json()[0]).unwrap();
+        assert!(lightning
+            .addresses
+            .lightning("BTC")
+            .unwrap()
+            .starts_with("lntb"));
+        assert_eq!(lightning.addresses.lnurl_pay("BTC"), None);
+        assert!(lightning.payment_codes.lnurl_pay("BTC").is_some());
+        assert_eq!(lightning.payment_totals.lightning("BTC"), Some(&3725.0));
+
+        let on_chain: Invoice = serde_json::from_str(get_test_invoices_json()[1]).unwrap();
+        assert_eq!(
+            on_chain.addresses.on_chain("LTC").map(String::as_str),
+            Some("tltc1qxzv66jwlm5pphum53alctrsdr7stfahcrl3cfp")
+        );
+        assert_eq!(
+            on_chain.payment_subtotals.on_chain("LTC"),
+            Some(&44369744.0)
+        );
+        assert_eq!(
+            on_chain.crypto_info[1].payment_method_id(),
+            PaymentMethodId::on_chain("LTC")
+        );
+    }
+
     #[test]
     fn test_parse_invoice() {
         get_test_invoices_json().iter().for_each(|string_json| {
@@ -174,7 +380,7 @@ mod test {
         });
     }
 
-    fn get_test_invoices_json() -> [&'static str; 2] {
+    pub(crate) fn get_test_invoices_json() -> [&'static str; 2] {
         [
             r#"{"addresses":{"BTC_LNURLPAY":null,"BTC_LightningLike":"lntb37250n1pjt27akpp5dh0yfefyvek68frjl886hnxwcrkwvrkd9cdnmke8j0l4fpqf2fxqdpj2pskjepqw3hjq4r9wd6zqum5daex2gpgfaexgetjypy5gw3q9ycqzzsxqzuysp5ealwkddt9phg7vsqu7k9xh373r0mcjc6kktwjmfnrhlkmk64tmyq9qyyssqwaazrhey5kmy09j83u2x6wsvjcqs8cwrz798ka5lfzfws8w5u2s5a32yavtkw6n6hj8zpk84np7kfttvuwmjc0tgpxvdq2ak20jsevcq3ujhlp"},"amountPaid":0,"bitcoinAddress":null,"btcDue":null,"btcPaid":null,"btcPrice":null,"buyer":{"address1":null,"address2":null,"country":null,"email":null,"locality":null,"name":null,"phone":null,"postalCode":null,"region":null},"buyerPaidBtcMinerFee":null,"buyerTotalBtcAmount":null,"checkoutType":null,"cryptoInfo":[{"address":null,"cryptoCode":"BTC","cryptoPaid":"0.00000000","due":"0.00003725","exRates":{"EUR":0},"networkFee":"0.00000000","paid":"0.00000000","paymentType":"LNURLPAY","paymentUrls":{"BIP21":null,"BIP72":null,"BIP72b":null,"BIP73":null,"BOLT11":null,"LNURLP":"lightning:lnurl1dp68gurn8ghj7ar9wd6xuet59ejx2mt09e38gcmsv9uhxetjwejhytn0wfnj7sj5gvh42j2vfe24ynp0wpshjtmf9umhzvj5fpvkgvnegf2hz4mex3p8we2k2a4x2s0vlde"},"payments":[],"price":"0.00003725","rate":26850.4,"totalDue":"0.00003725","txCount":0,"url":"https://testnet.demo.btcpayserver.org/i/BTC_LNURLPAY/7q2THYd2yBUqWy4BweVWje"},{"address":"lntb37250n1pjt27akpp5dh0yfefyvek68frjl886hnxwcrkwvrkd9cdnmke8j0l4fpqf2fxqdpj2pskjepqw3hjq4r9wd6zqum5daex2gpgfaexgetjypy5gw3q9ycqzzsxqzuysp5ealwkddt9phg7vsqu7k9xh373r0mcjc6kktwjmfnrhlkmk64tmyq9qyyssqwaazrhey5kmy09j83u2x6wsvjcqs8cwrz798ka5lfzfws8w5u2s5a32yavtkw6n6hj8zpk84np7kfttvuwmjc0tgpxvdq2ak20jsevcq3ujhlp","cryptoCode":"BTC","cryptoPaid":"0.00000000","due":"0.00003725","exRates":{"EUR":0},"networkFee":"0.00000000","paid":"0.00000000","paymentType":"LightningLike","paymentUrls":{"BIP21":null,"BIP72":null,"BIP72b":null,"BIP73":null,"BOLT11":"lightning:lntb37250n1pjt27akpp5dh0yfefyvek68frjl886hnxwcrkwvrkd9cdnmke8j0l4fpqf2fxqdpj2pskjepqw3hjq4r9wd6zqum5daex2gpgfaexgetjypy5gw3q9ycqzzsxqzuysp5ealwkddt9phg7vsqu7k9xh373r0mcjc6kktwjmfnrhlkmk64tmyq9qyyssqwaazrhey5kmy09j83u2x6wsvjcqs8cwrz798ka5lfzfws8w5u2s5a32yavtkw6n6hj8zpk84np7kfttvuwmjc0tgpxvdq2ak20jsevcq3ujhlp"},"payments":[],"price":"0.00003725","rate":26850.4,"totalDue":"0.00003725","txCount":0,"url":"https://testnet.demo.btcpayserver.org/i/BTC_LightningLike/7q2THYd2yBUqWy4BweVWje"}],"currency":"EUR","currentTime":1689615287973,"exRates":null,"exceptionStatus":false,"exchangeRates":{"BTC":{"EUR":0}},"expirationTime":1689616186000,"flags":null,"guid":"0c9318b6-c197-42de-b155-dfacb72cc381","id":"7q2THYd2yBUqWy4BweVWje","invoiceTime":1689615286000,"itemCode":null,"itemDesc":null,"lowFeeDetected":false,"minerFees":{},"orderId":null,"paymentCodes":{"BTC_LNURLPAY":{"BIP21":null,"BIP72":null,"BIP72b":null,"BIP73":null,"BOLT11":null,"LNURLP":"lightning:lnurl1dp68gurn8ghj7ar9wd6xuet59ejx2mt09e38gcmsv9uhxetjwejhytn0wfnj7sj5gvh42j2vfe24ynp0wpshjtmf9umhzvj5fpvkgvnegf2hz4mex3p8we2k2a4x2s0vlde"},"BTC_LightningLike":{"BIP21":null,"BIP72":null,"BIP72b":null,"BIP73":null,"BOLT11":"lightning:lntb37250n1pjt27akpp5dh0yfefyvek68frjl886hnxwcrkwvrkd9cdnmke8j0l4fpqf2fxqdpj2pskjepqw3hjq4r9wd6zqum5daex2gpgfaexgetjypy5gw3q9ycqzzsxqzuysp5ealwkddt9phg7vsqu7k9xh373r0mcjc6kktwjmfnrhlkmk64tmyq9qyyssqwaazrhey5kmy09j83u2x6wsvjcqs8cwrz798ka5lfzfws8w5u2s5a32yavtkw6n6hj8zpk84np7kfttvuwmjc0tgpxvdq2ak20jsevcq3ujhlp"}},"paymentSubtotals":{"BTC_LNURLPAY":3725,"BTC_LightningLike":3725},"paymentTotals":{"BTC_LNURLPAY":3725,"BTC_LightningLike":3725},"paymentUrls":null,"posData":null,"price":1,"rate":0,"refundAddressRequestPending":false,"status":"new","supportedTransactionCurrencies":{"BTC":{"enabled":true,"reason":null}},"token":"5GYd8KW4QZg9Sw4iagNSk5","url":"https://testnet.demo.btcpayserver.org/invoice?id=7q2THYd2yBUqWy4BweVWje"}"#,
             r#"{"url":"https://testnet.demo.btcpayserver.org/invoice?id=3Wy4cKQEvmsBru5yccGbNn","posData":null,"status":"new","btcPrice":"0.00260930","btcDue":"0.00260930","cryptoInfo":[{"cryptoCode":"BTC","paymentType":"BTCLike","rate":7664.908,"exRates":{"USD":0},"paid":"0.00000000","price":"0.00260930","due":"0.00260930","paymentUrls":{"BIP21":"bitcoin:tb1qxzv66jwlm5pphum53alctrsdr7stfahc6hnxeg?amount=0.0026093","BIP72":null,"BIP72b":null,"BIP73":null,"BOLT11":null},"address":"tb1qxzv66jwlm5pphum53alctrsdr7stfahc6hnxeg","url":"https://testnet.demo.btcpayserver.org/i/BTC/3Wy4cKQEvmsBru5yccGbNn","totalDue":"0.00260930","networkFee":"0.00000000","txCount":0,"cryptoPaid":"0.00000000","payments":[]},{"cryptoCode":"LTC","paymentType":"BTCLike","rate":45.07576229821518,"exRates":{"USD":0},"paid":"0.00000000","price":"0.44369744","due":"0.44369744","paymentUrls":{"BIP21":"litecoin:tltc1qxzv66jwlm5pphum53alctrsdr7stfahcrl3cfp?amount=0.44369744","BIP72":null,"BIP72b":null,"BIP73":null,"BOLT11":null},"address":"tltc1qxzv66jwlm5pphum53alctrsdr7stfahcrl3cfp","url":"https://testnet.demo.btcpayserver.org/i/LTC/3Wy4cKQEvmsBru5yccGbNn","totalDue":"0.44369744","networkFee":"0.00000000","txCount":0,"cryptoPaid":"0.00000000","payments":[]}],"price":20,"currency":"USD","exRates":{"USD":0},"buyerTotalBtcAmount":null,"itemDesc":null,"itemCode":null,"orderId":null,"guid":"52ccf34a-f69f-4ccf-8027-46bec72a57c7","id":"3Wy4cKQEvmsBru5yccGbNn","invoiceTime":1587907315000,"expirationTime":1587908215000,"currentTime":1587907528578,"lowFeeDetected":false,"btcPaid":"0.00000000","rate":7664.908,"exceptionStatus":false,"paymentUrls":{"BIP21":"bitcoin:tb1qxzv66jwlm5pphum53alctrsdr7stfahc6hnxeg?amount=0.0026093","BIP72":null,"BIP72b":null,"BIP73":null,"BOLT11":null},"refundAddressRequestPending":false,"buyerPaidBtcMinerFee":null,"bitcoinAddress":"tb1qxzv66jwlm5pphum53alctrsdr7stfahc6hnxeg","token":"8byQ85L8bX4Urq68ne3Wg7","flags":{"refundable":false},"paymentSubtotals":{"BTC":260930,"LTC":44369744},"paymentTotals":{"BTC":260930,"LTC":44369744},"amountPaid":0,"minerFees":{"BTC":{"satoshisPerByte":1,"totalFee":0},"LTC":{"satoshisPerByte":25,"totalFee":0}},"exchangeRates":{"BTC":{"USD":0},"LTC":{"USD":0}},"supportedTransactionCurrencies":{"BTC":{"enabled":true,"reason":null},"LTC":{"enabled":true,"reason":null}},"addresses":{"BTC":"tb1qxzv66jwlm5pphum53alctrsdr7stfahc6hnxeg","LTC":"tltc1qxzv66jwlm5pphum53alctrsdr7stfahcrl3cfp"},"paymentCodes":{"BTC":{"BIP21":"bitcoin:tb1qxzv66jwlm5pphum53alctrsdr7stfahc6hnxeg?amount=0.0026093","BIP72":null,"BIP72b":null,"BIP73":null,"BOLT11":null},"LTC":{"BIP21":"litecoin:tltc1qxzv66jwlm5pphum53alctrsdr7stfahcrl3cfp?amount=0.44369744","BIP72":null,"BIP72b":null,"BIP73":null,"BOLT11":null}},"buyer":{"name":null,"address1":null,"address2":null,"locality":null,"region":null,"postalCode":null,"country":null,"phone":null,"email":null}}"#,
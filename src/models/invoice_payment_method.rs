@@ -0,0 +1,227 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use super::payment_method::{PaymentMethodId, PaymentMethods};
+use super::timestamp::{self, DateTime};
+use crate::core::client::Error;
+
+string_enum! {
+    pub enum GreenfieldPaymentStatus {
+        /// Double spent or otherwise rejected, not counted towards the invoice
+        Invalid => "Invalid",
+        /// Seen but not confirmed enough for the store speed policy
+        Processing => "Processing",
+        Settled => "Settled",
+    }
+}
+
+/// Payment received on an invoice. Amounts are decimal strings in the currency of the method.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GreenfieldPayment {
+    pub id: String,
+    #[serde(with = "timestamp::seconds")]
+    pub received_date: DateTime,
+    pub value: String,
+    #[serde(default)]
+    pub fee: Option<String>,
+    pub status: GreenfieldPaymentStatus,
+    pub destination: String,
+}
+
+/// Payment method of a Greenfield invoice, with what the buyer has to pay through it.
+///
+/// Amounts are decimal strings in the currency of the method, except `total_paid` which sums up
+/// the payments received through every method of the invoice.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InvoicePaymentMethod {
+    #[serde(alias = "paymentMethodId")]
+    pub payment_method: PaymentMethodId,
+    #[serde(alias = "currency")]
+    pub crypto_code: String,
+    /// Address, BOLT11 invoice or LNURL, depending on the method
+    pub destination: String,
+    pub payment_link: Option<String>,
+    /// Rate from the invoice currency to the currency of the method
+    pub rate: String,
+    /// Paid through this method
+    pub payment_method_paid: String,
+    /// Paid through every method
+    pub total_paid: String,
+    pub due: String,
+    /// Total to pay, network fee included
+    pub amount: String,
+    #[serde(alias = "paymentMethodFee", default)]
+    pub network_fee: Option<String>,
+    #[serde(default)]
+    pub payments: Vec<GreenfieldPayment>,
+    /// Whether the destination has been generated, see `activate_payment_method`
+    #[serde(default = "default_activated")]
+    pub activated: bool,
+    #[serde(default)]
+    pub additional_data: serde_json::Value,
+
+    /// Fields not known to this version of the library
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
+}
+
+fn default_activated() -> bool {
+    true
+}
+
+fn parse_amount(amount: &str) -> Result<Decimal, Error> {
+    Decimal::from_str(amount).map_err(|_| Error::InvalidResponse)
+}
+
+impl InvoicePaymentMethod {
+    /// Sums up the payments of this method by status
+    pub fn summary(&self) -> Result<PaymentMethodSummary, Error> {
+        let mut summary = PaymentMethodSummary {
+            amount: parse_amount(&self.amount)?,
+            due: parse_amount(&self.due)?,
+            paid: Decimal::ZERO,
+            pending: Decimal::ZERO,
+        };
+
+        for payment in &self.payments {
+            let value = parse_amount(&payment.value)?;
+            match payment.status {
+                GreenfieldPaymentStatus::Settled => summary.paid += value,
+                GreenfieldPaymentStatus::Processing => summary.pending += value,
+                _ => {}
+            }
+        }
+
+        Ok(summary)
+    }
+}
+
+/// Amounts of a payment method, in its currency. Decimals are exact, so that payments add up to
+/// the amount due.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PaymentMethodSummary {
+    /// Total to pay, network fee included
+    pub amount: Decimal,
+    /// Still to be paid, as computed by the server across every method
+    pub due: Decimal,
+    /// Received and settled
+    pub paid: Decimal,
+    /// Received but still waiting for confirmations
+    pub pending: Decimal,
+}
+
+impl PaymentMethodSummary {
+    pub fn is_paid(&self) -> bool {
+        self.due <= Decimal::ZERO
+    }
+
+    /// Something was received through this method, but not enough to cover the invoice
+    pub fn is_partially_paid(&self) -> bool {
+        self.paid + self.pending > Decimal::ZERO && !self.is_paid()
+    }
+}
+
+/// Summary of every payment method of an invoice
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct InvoicePaymentSummary {
+    pub methods: PaymentMethods<PaymentMethodSummary>,
+}
+
+impl InvoicePaymentSummary {
+    pub fn new(methods: &[InvoicePaymentMethod]) -> Result<Self, Error> {
+        let mut summary = InvoicePaymentSummary::default();
+        for method in methods {
+            summary
+                .methods
+                .insert(method.payment_method.clone(), method.summary()?);
+        }
+
+        Ok(summary)
+    }
+
+    pub fn is_paid(&self) -> bool {
+        !self.methods.is_empty() && self.methods.values().all(PaymentMethodSummary::is_paid)
+    }
+
+    /// Something was received, through any method, but not enough to cover the invoice
+    pub fn is_partially_paid(&self) -> bool {
+        !self.is_paid()
+            && self
+                .methods
+                .values()
+                .any(|m| m.paid + m.pending > Decimal::ZERO)
+    }
+
+    /// Whether some of the payments are still waiting for confirmations
+    pub fn has_pending(&self) -> bool {
+        self.methods.values().any(|m| m.pending > Decimal::ZERO)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+
+    pub const PAYMENT_METHODS_JSON: &str = r#"[{"paymentMethod":"BTC","cryptoCode":"BTC","destination":"bc1qxy2kgdygjrsqtzq2n0yrf2493p83kkfjhx0wlh","paymentLink":"bitcoin:bc1qxy2kgdygjrsqtzq2n0yrf2493p83kkfjhx0wlh?amount=0.00067","rate":"30000.00","paymentMethodPaid":"0.0004","totalPaid":"0.0004","due":"0.00027","amount":"0.00067","networkFee":"0.00001","payments":[{"id":"d5e2a7b1c1f7cbd1ef3fe7c2b1dc0b3e4f0d1a9c8b7e6f5a4d3c2b1a0f9e8d7c-0","receivedDate":1689615400,"value":"0.0003","fee":"0.00001","status":"Settled","destination":"bc1qxy2kgdygjrsqtzq2n0yrf2493p83kkfjhx0wlh"},{"id":"0b3e4f0d1a9c8b7e6f5a4d3c2b1a0f9e8d7cd5e2a7b1c1f7cbd1ef3fe7c2b1dc-1","receivedDate":1689615500,"value":"0.0001","fee":"0.00001","status":"Processing","destination":"bc1qxy2kgdygjrsqtzq2n0yrf2493p83kkfjhx0wlh"}],"activated":true,"additionalData":{"providedComment":null}},{"paymentMethodId":"BTC-LN","currency":"BTC","destination":"lnbc270u1pj","paymentLink":"lightning:lnbc270u1pj","rate":"30000.00","paymentMethodPaid":"0","totalPaid":"0.0004","due":"0.00027","amount":"0.00027","paymentMethodFee":"0","payments":[],"activated":true}]"#;
+
+    #[test]
+    fn test_parse_payment_methods() {
+        let methods: Vec<InvoicePaymentMethod> =
+            serde_json::from_str(PAYMENT_METHODS_JSON).unwrap();
+
+        assert_eq!(methods[0].payment_method, PaymentMethodId::on_chain("BTC"));
+        assert_eq!(
+            methods[0].payments[1].status,
+            GreenfieldPaymentStatus::Processing
+        );
+        assert_eq!(methods[1].payment_method, PaymentMethodId::lightning("BTC"));
+        assert_eq!(methods[1].crypto_code, "BTC");
+        assert_eq!(methods[1].network_fee.as_deref(), Some("0"));
+    }
+
+    #[test]
+    fn test_payment_summary() {
+        let methods: Vec<InvoicePaymentMethod> =
+            serde_json::from_str(PAYMENT_METHODS_JSON).unwrap();
+        let summary = InvoicePaymentSummary::new(&methods).unwrap();
+
+        let on_chain = summary.methods.on_chain("BTC").unwrap();
+        assert_eq!(on_chain.paid, parse_amount("0.0003").unwrap());
+        assert_eq!(on_chain.pending, parse_amount("0.0001").unwrap());
+        assert!(on_chain.is_partially_paid());
+        assert!(!summary
+            .methods
+            .lightning("BTC")
+            .unwrap()
+            .is_partially_paid());
+
+        assert!(summary.is_partially_paid());
+        assert!(summary.has_pending());
+        assert!(!summary.is_paid());
+    }
+
+    #[test]
+    fn test_payments_sum_to_amount() {
+        let mut methods: Vec<InvoicePaymentMethod> =
+            serde_json::from_str(PAYMENT_METHODS_JSON).unwrap();
+        let method = &mut methods[0];
+        method.amount = "0.3".into();
+        method.due = "0".into();
+        let payment = method.payments[0].clone();
+        method.payments = vec![payment; 3];
+        for payment in &mut method.payments {
+            payment.value = "0.1".into();
+        }
+
+        // 0.1 + 0.1 + 0.1 != 0.3 with floats
+        let summary = method.summary().unwrap();
+        assert_eq!(summary.paid, summary.amount);
+        assert!(summary.is_paid());
+        assert!(!summary.is_partially_paid());
+    }
+}
@@ -0,0 +1,202 @@
+use super::invoice::{ExceptionStatus, Invoice, InvoiceStatus};
+use crate::core::client::Error;
+
+impl InvoiceStatus {
+    /// Statuses the server moves to on its own from `self`
+    fn next(&self) -> &'static [InvoiceStatus] {
+        match self {
+            InvoiceStatus::New => &[InvoiceStatus::Paid, InvoiceStatus::Expired],
+            InvoiceStatus::Paid => &[InvoiceStatus::Confirmed, InvoiceStatus::Invalid],
+            InvoiceStatus::Confirmed => &[InvoiceStatus::Completed],
+            _ => &[],
+        }
+    }
+
+    /// Whether the server will never move the invoice out of this status on its own. The merchant
+    /// can still mark it as completed or invalid.
+    ///
+    /// Payments received after the expiration don't move an `Expired` invoice, they only set the
+    /// exception status to `PaidLate` or `PaidPartial`.
+    pub fn is_terminal(&self) -> bool {
+        matches!(
+            self,
+            InvoiceStatus::Expired | InvoiceStatus::Completed | InvoiceStatus::Invalid
+        )
+    }
+
+    /// Whether the server can move an invoice from `self` straight to `next`. Moving to the same
+    /// status is always allowed.
+    pub fn can_transition_to(&self, next: &InvoiceStatus) -> bool {
+        self == next || self.next().contains(next)
+    }
+
+    /// Whether `next` can follow `self` through any number of legal transitions. When polling,
+    /// intermediate statuses can be missed, so this is the check to use there.
+    pub fn can_reach(&self, next: &InvoiceStatus) -> bool {
+        self == next || self.next().iter().any(|status| status.can_reach(next))
+    }
+}
+
+/// Tracks the status of an invoice as it is observed through IPNs or polling, rejecting the
+/// changes the server can't make.
+///
+/// Besides the normal flow (`new` → `paid` → `confirmed` → `completed`, `new` → `expired` and
+/// `paid` → `invalid`), the merchant can mark any invoice as completed or invalid, which the server
+/// reports with the `Marked` exception status. Statuses unknown to this version of the library
+/// are accepted without checks.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvoiceLifecycle {
+    status: InvoiceStatus,
+    exception_status: ExceptionStatus,
+    /// Whether missed intermediate statuses are tolerated
+    allow_skips: bool,
+}
+
+impl Default for InvoiceLifecycle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl InvoiceLifecycle {
+    /// Lifecycle of a new invoice, checked transition by transition as with IPNs
+    pub fn new() -> Self {
+        Self::with_status(InvoiceStatus::New, ExceptionStatus::None)
+    }
+
+    pub fn with_status(status: InvoiceStatus, exception_status: ExceptionStatus) -> Self {
+        InvoiceLifecycle {
+            status,
+            exception_status,
+            allow_skips: false,
+        }
+    }
+
+    pub fn from_invoice(invoice: &Invoice) -> Self {
+        Self::with_status(invoice.status.clone(), invoice.exception_status.clone())
+    }
+
+    /// Tolerates statuses that were skipped between two observations, like `paid` → `completed`
+    /// when polling misses `confirmed`
+    pub fn allow_skips(mut self, allow_skips: bool) -> Self {
+        self.allow_skips = allow_skips;
+        self
+    }
+
+    pub fn status(&self) -> &InvoiceStatus {
+        &self.status
+    }
+
+    pub fn exception_status(&self) -> &ExceptionStatus {
+        &self.exception_status
+    }
+
+    pub fn is_terminal(&self) -> bool {
+        self.status.is_terminal()
+    }
+
+    /// Whether the invoice expired but was then paid in full. The server doesn't move it to
+    /// `paid`, so the merchant has to decide what to do with the payment.
+    pub fn is_paid_late(&self) -> bool {
+        self.status == InvoiceStatus::Expired && self.exception_status == ExceptionStatus::PaidLate
+    }
+
+    /// Whether the server can report `status` with `exception_status` after the current state
+    pub fn is_legal(&self, status: &InvoiceStatus, exception_status: &ExceptionStatus) -> bool {
+        let marked = *exception_status == ExceptionStatus::Marked
+            && matches!(status, InvoiceStatus::Completed | InvoiceStatus::Invalid);
+
+        match (&self.status, status) {
+            (InvoiceStatus::Unknown(_), _) | (_, InvoiceStatus::Unknown(_)) => true,
+            _ if marked => true,
+            (current, next) if self.allow_skips => current.can_reach(next),
+            (current, next) => current.can_transition_to(next),
+        }
+    }
+
+    /// Records a new observation, returning whether the status changed. Illegal transitions are
+    /// reported as `Error::IllegalTransition` and leave the lifecycle untouched.
+    pub fn observe(
+        &mut self,
+        status: InvoiceStatus,
+        exception_status: ExceptionStatus,
+    ) -> Result<bool, Error> {
+        if !self.is_legal(&status, &exception_status) {
+            return Err(Error::IllegalTransition(self.status.clone(), status));
+        }
+
+        let changed = self.status != status;
+        self.status = status;
+        self.exception_status = exception_status;
+
+        Ok(changed)
+    }
+
+    pub fn observe_invoice(&mut self, invoice: &Invoice) -> Result<bool, Error> {
+        self.observe(invoice.status.clone(), invoice.exception_status.clone())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_transitions() {
+        assert!(InvoiceStatus::New.can_transition_to(&InvoiceStatus::Paid));
+        assert!(InvoiceStatus::Paid.can_transition_to(&InvoiceStatus::Invalid));
+        assert!(!InvoiceStatus::New.can_transition_to(&InvoiceStatus::Completed));
+        assert!(InvoiceStatus::New.can_reach(&InvoiceStatus::Completed));
+        assert!(!InvoiceStatus::Expired.can_reach(&InvoiceStatus::Paid));
+        assert!(!InvoiceStatus::Completed.can_reach(&InvoiceStatus::Confirmed));
+
+        assert!(InvoiceStatus::Invalid.is_terminal());
+        assert!(!InvoiceStatus::Confirmed.is_terminal());
+    }
+
+    #[test]
+    fn test_lifecycle() {
+        let mut lifecycle = InvoiceLifecycle::new();
+        assert!(lifecycle
+            .observe(InvoiceStatus::Paid, ExceptionStatus::PaidOver)
+            .unwrap());
+        assert!(!lifecycle
+            .observe(InvoiceStatus::Paid, ExceptionStatus::PaidOver)
+            .unwrap());
+        assert!(matches!(
+            lifecycle.observe(InvoiceStatus::Completed, ExceptionStatus::None),
+            Err(Error::IllegalTransition(
+                InvoiceStatus::Paid,
+                InvoiceStatus::Completed
+            ))
+        ));
+        assert_eq!(lifecycle.status(), &InvoiceStatus::Paid);
+
+        let mut polled = lifecycle.clone().allow_skips(true);
+        polled
+            .observe(InvoiceStatus::Completed, ExceptionStatus::None)
+            .unwrap();
+        assert!(polled.is_terminal());
+        assert!(polled
+            .observe(InvoiceStatus::Invalid, ExceptionStatus::Marked)
+            .unwrap());
+    }
+
+    #[test]
+    fn test_late_payment() {
+        let mut lifecycle = InvoiceLifecycle::new();
+        lifecycle
+            .observe(InvoiceStatus::Expired, ExceptionStatus::None)
+            .unwrap();
+        assert!(!lifecycle
+            .observe(InvoiceStatus::Expired, ExceptionStatus::PaidLate)
+            .unwrap());
+        assert!(lifecycle.is_paid_late());
+        assert!(lifecycle
+            .observe(InvoiceStatus::Paid, ExceptionStatus::PaidLate)
+            .is_err());
+        assert!(lifecycle
+            .observe(InvoiceStatus::Completed, ExceptionStatus::Marked)
+            .is_ok());
+    }
+}
@@ -1,7 +1,33 @@
+#[macro_use]
+mod macros;
+
+pub mod app;
 pub mod client;
+pub mod greenfield_invoice;
 pub mod invoice;
+pub mod invoice_payment_method;
+pub mod lifecycle;
+pub mod network;
+pub mod notification;
+pub mod payment_method;
+pub mod payment_uri;
 pub mod rate;
+pub mod server;
+pub mod timestamp;
+pub mod user;
+pub mod webhook;
 
+pub use app::*;
 pub use client::*;
+pub use greenfield_invoice::*;
 pub use invoice::*;
+pub use invoice_payment_method::*;
+pub use lifecycle::*;
+pub use notification::*;
+pub use payment_method::*;
+pub use payment_uri::*;
 pub use rate::*;
+pub use server::*;
+pub use timestamp::DateTime;
+pub use user::*;
+pub use webhook::*;
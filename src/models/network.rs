@@ -0,0 +1,175 @@
+use bitcoin::address::{Address, NetworkUnchecked};
+use bitcoin::Network;
+
+use super::invoice::Invoice;
+use super::payment_method::PaymentType;
+use super::payment_uri::Bolt11Invoice;
+use crate::core::client::Error;
+
+fn parse_address(address: &str) -> Result<Address<NetworkUnchecked>, Error> {
+    address
+        .parse()
+        .map_err(|_| Error::InvalidAddress(address.to_string()))
+}
+
+fn check_address(address: &str, network: Network) -> Result<Address, Error> {
+    parse_address(address)?
+        .require_network(network)
+        .map_err(|_| Error::NetworkMismatch(network, address.to_string()))
+}
+
+/// Guesses the network of an address from its prefix. Testnet and signet share their prefixes, so
+/// both are reported as testnet.
+pub fn detect_address_network(address: &str) -> Option<Network> {
+    let address = parse_address(address).ok()?;
+
+    [Network::Bitcoin, Network::Testnet, Network::Regtest]
+        .iter()
+        .copied()
+        .find(|network| address.is_valid_for_network(*network))
+}
+
+impl Invoice {
+    /// BTC on-chain addresses and Lightning invoices, from all the places the server reports them
+    fn btc_destinations(&self) -> (Vec<&str>, Vec<&str>) {
+        let mut on_chain = Vec::new();
+        let mut lightning = Vec::new();
+
+        on_chain.extend(self.bitcoin_address.as_deref());
+        for (id, destination) in self.addresses.iter() {
+            match id.payment_type {
+                _ if id.crypto_code != "BTC" => {}
+                PaymentType::BTCLike => on_chain.push(destination.as_str()),
+                PaymentType::LightningLike => lightning.push(destination.as_str()),
+                _ => {}
+            }
+        }
+        for info in self.crypto_info.iter().filter(|i| i.crypto_code == "BTC") {
+            match (&info.payment_type, &info.address) {
+                (PaymentType::BTCLike, Some(address)) => on_chain.push(address.as_str()),
+                (PaymentType::LightningLike, Some(invoice)) => lightning.push(invoice.as_str()),
+                _ => {}
+            }
+        }
+
+        (on_chain, lightning)
+    }
+
+    /// Guesses the network from the invoice's BTC addresses or Lightning invoices
+    pub fn detect_network(&self) -> Option<Network> {
+        let (on_chain, lightning) = self.btc_destinations();
+
+        // Lightning prefixes tell regtest apart from testnet, so they are preferred
+        lightning
+            .iter()
+            .filter_map(|i| i.parse::<Bolt11Invoice>().ok()?.network())
+            .chain(on_chain.iter().filter_map(|a| detect_address_network(a)))
+            .next()
+    }
+
+    /// Makes sure every BTC address and Lightning invoice belongs to `network`. Destinations that
+    /// can't be parsed are skipped, like in [`detect_network`](Self::detect_network), so that an
+    /// unusual format doesn't make the invoice unusable.
+    pub fn check_network(&self, network: Network) -> Result<(), Error> {
+        let (on_chain, lightning) = self.btc_destinations();
+
+        for address in on_chain {
+            match check_address(address, network) {
+                Err(Error::InvalidAddress(_)) => {}
+                result => {
+                    result?;
+                }
+            }
+        }
+        for invoice in lightning {
+            let parsed = match invoice.parse::<Bolt11Invoice>() {
+                Ok(parsed) => parsed,
+                Err(_) => continue,
+            };
+            let matches = match parsed.network() {
+                // Signet invoices are sometimes issued with the testnet prefix
+                Some(Network::Testnet) => matches!(network, Network::Testnet | Network::Signet),
+                Some(found) => found == network,
+                None => false,
+            };
+            if !matches {
+                return Err(Error::NetworkMismatch(network, invoice.to_string()));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Parses `bitcoin_address`, checking that it belongs to `network`
+    pub fn checked_bitcoin_address(&self, network: Network) -> Result<Option<Address>, Error> {
+        self.bitcoin_address
+            .as_deref()
+            .map(|address| check_address(address, network))
+            .transpose()
+    }
+
+    /// Parses the on-chain address for `crypto_code` in `addresses`, checking that it belongs to
+    /// `network`. Only meaningful for BTC.
+    pub fn checked_on_chain_address(
+        &self,
+        crypto_code: &str,
+        network: Network,
+    ) -> Result<Option<Address>, Error> {
+        self.addresses
+            .on_chain(crypto_code)
+            .map(|address| check_address(address, network))
+            .transpose()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::models::invoice::test::get_test_invoices_json;
+
+    #[test]
+    fn test_detect_network() {
+        let lightning: Invoice = serde_json::from_str(get_test_invoices_json()[0]).unwrap();
+        let on_chain: Invoice = serde_json::from_str(get_test_invoices_json()[1]).unwrap();
+
+        assert_eq!(lightning.detect_network(), Some(Network::Testnet));
+        assert_eq!(on_chain.detect_network(), Some(Network::Testnet));
+        assert_eq!(
+            detect_address_network("bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq"),
+            Some(Network::Bitcoin)
+        );
+    }
+
+    #[test]
+    fn test_check_network() {
+        let lightning: Invoice = serde_json::from_str(get_test_invoices_json()[0]).unwrap();
+        let on_chain: Invoice = serde_json::from_str(get_test_invoices_json()[1]).unwrap();
+
+        lightning.check_network(Network::Testnet).unwrap();
+        on_chain.check_network(Network::Testnet).unwrap();
+        assert!(on_chain
+            .checked_on_chain_address("BTC", Network::Testnet)
+            .unwrap()
+            .is_some());
+
+        assert!(matches!(
+            lightning.check_network(Network::Bitcoin),
+            Err(Error::NetworkMismatch(Network::Bitcoin, _))
+        ));
+        assert!(matches!(
+            on_chain.checked_bitcoin_address(Network::Bitcoin),
+            Err(Error::NetworkMismatch(Network::Bitcoin, _))
+        ));
+        assert!(matches!(
+            check_address("tb1qnotanaddress", Network::Testnet),
+            Err(Error::InvalidAddress(_))
+        ));
+
+        let mut json: serde_json::Value =
+            serde_json::from_str(get_test_invoices_json()[0]).unwrap();
+        json["addresses"]["BTC_LightningLike"] = "lntb1unparsable".into();
+        json["addresses"]["BTC"] = "tb1qnotanaddress".into();
+        let unparsable: Invoice = serde_json::from_value(json).unwrap();
+        unparsable.check_network(Network::Testnet).unwrap();
+    }
+}
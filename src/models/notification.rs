@@ -0,0 +1,91 @@
+use serde::{Deserialize, Serialize};
+
+use super::timestamp::{self, DateTime};
+
+string_enum! {
+    pub enum NotificationType {
+        /// A new version of BTCPay Server is available
+        NewVersion => "newversion",
+        /// An invoice changed state, see the `identifier` for which event
+        InvoiceState => "invoicestate",
+        /// A payout is awaiting approval or payment
+        Payout => "payout",
+        ExternalPayoutTransaction => "external-payout-transaction",
+        NewUserRequiresApproval => "newuserrequiresapproval",
+        InviteAccepted => "inviteaccepted",
+        PluginUpdate => "pluginupdate",
+        Junk => "junk",
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Notification {
+    pub id: String,
+    /// Finer grained kind of the notification, like `payout_awaitingapproval`
+    pub identifier: String,
+    #[serde(rename = "type")]
+    pub notification_type: NotificationType,
+    /// Human-readable message
+    pub body: String,
+    pub store_id: Option<String>,
+    pub app_id: Option<String>,
+    pub link: Option<String>,
+    #[serde(default)]
+    pub seen: bool,
+    #[serde(with = "timestamp::seconds")]
+    pub created_time: DateTime,
+}
+
+/// Filters for [`get_notifications`](crate::BTCPayClient::get_notifications)
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetNotificationsArgs {
+    /// Only seen notifications with `Some(true)`, only unseen ones with `Some(false)`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seen: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub skip: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub take: Option<usize>,
+}
+
+impl GetNotificationsArgs {
+    pub fn unseen() -> Self {
+        GetNotificationsArgs {
+            seen: Some(false),
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+
+    pub const NOTIFICATIONS_JSON: &str = r#"[{"id":"0c6f5f1e-7b0a-4e55-8f1f-0e4bd7a1b2c3","identifier":"newversion","type":"newversion","body":"New version 1.11.8 released!","storeId":null,"link":"https://github.com/btcpayserver/btcpayserver/releases/tag/v1.11.8","seen":false,"createdTime":1689615287},{"id":"5d1c8a9b-2e3f-4a6b-9c7d-1e2f3a4b5c6d","identifier":"payout_awaitingapproval","type":"payout","body":"A new payout is awaiting for approval","storeId":"8vZCm1j3mNy2BcUVCgTbQaGzzRd1V1tXxpuNwDLfprbi","link":"https://testnet.demo.btcpayserver.org/stores/8vZCm1j3mNy2BcUVCgTbQaGzzRd1V1tXxpuNwDLfprbi/payouts?payoutState=AwaitingApproval","seen":false,"createdTime":1689615300},{"id":"9f8e7d6c-5b4a-4392-8170-6f5e4d3c2b1a","identifier":"invoicestate","type":"invoicestate","body":"Invoice 3Wy4cKQEvmsBru5yccGbNn has expired","storeId":"8vZCm1j3mNy2BcUVCgTbQaGzzRd1V1tXxpuNwDLfprbi","link":"https://testnet.demo.btcpayserver.org/invoices/3Wy4cKQEvmsBru5yccGbNn","seen":true,"createdTime":1689615400}]"#;
+
+    #[test]
+    fn test_parse_notifications() {
+        let notifications: Vec<Notification> = serde_json::from_str(NOTIFICATIONS_JSON).unwrap();
+
+        assert_eq!(
+            notifications[0].notification_type,
+            NotificationType::NewVersion
+        );
+        assert_eq!(
+            timestamp::to_millis(&notifications[0].created_time),
+            1689615287000
+        );
+        assert_eq!(notifications[1].notification_type, NotificationType::Payout);
+        assert_eq!(
+            notifications[2].notification_type,
+            NotificationType::InvoiceState
+        );
+        assert!(notifications[2].seen);
+        assert_eq!(
+            NotificationType::from("lightning-node-status"),
+            NotificationType::Unknown("lightning-node-status".into())
+        );
+    }
+}
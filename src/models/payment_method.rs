@@ -0,0 +1,214 @@
+use std::collections::HashMap;
+use std::ops::{Deref, DerefMut};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+string_enum! {
+    pub enum PaymentType {
+        BTCLike => "BTCLike",
+        LightningLike => "LightningLike",
+        LNURLPay => "LNURLPAY",
+    }
+}
+
+/// Identifies a payment method, like on-chain BTC or Lightning LTC.
+///
+/// Parses both the legacy format (`BTC`, `BTC_LightningLike`, `BTC_LNURLPAY`) and the Greenfield
+/// one (`BTC-CHAIN`, `BTC-LN`, `BTC-LNURL`, or `BTC-LightningNetwork` on older servers), and is
+/// always displayed in the legacy format.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct PaymentMethodId {
+    pub crypto_code: String,
+    pub payment_type: PaymentType,
+}
+
+impl PaymentMethodId {
+    pub fn new(crypto_code: &str, payment_type: PaymentType) -> Self {
+        PaymentMethodId {
+            crypto_code: crypto_code.to_string(),
+            payment_type,
+        }
+    }
+
+    pub fn on_chain(crypto_code: &str) -> Self {
+        Self::new(crypto_code, PaymentType::BTCLike)
+    }
+
+    pub fn lightning(crypto_code: &str) -> Self {
+        Self::new(crypto_code, PaymentType::LightningLike)
+    }
+
+    pub fn lnurl_pay(crypto_code: &str) -> Self {
+        Self::new(crypto_code, PaymentType::LNURLPay)
+    }
+
+    /// The id in the Greenfield format, as used in the paths of the Greenfield API
+    pub fn to_greenfield_id(&self) -> String {
+        let suffix = match &self.payment_type {
+            PaymentType::BTCLike => "CHAIN",
+            PaymentType::LightningLike => "LN",
+            PaymentType::LNURLPay => "LNURL",
+            PaymentType::Unknown(other) => other,
+        };
+
+        format!("{}-{}", self.crypto_code, suffix)
+    }
+}
+
+impl From<&str> for PaymentMethodId {
+    fn from(s: &str) -> Self {
+        if let Some((crypto_code, payment_type)) = s.split_once('_') {
+            return Self::new(crypto_code, payment_type.into());
+        }
+
+        match s.split_once('-') {
+            Some((crypto_code, suffix)) => {
+                let payment_type = match suffix {
+                    "CHAIN" | "OnChain" => PaymentType::BTCLike,
+                    "LN" | "LightningNetwork" => PaymentType::LightningLike,
+                    "LNURL" | "LNURLPAY" => PaymentType::LNURLPay,
+                    other => PaymentType::Unknown(other.to_string()),
+                };
+
+                Self::new(crypto_code, payment_type)
+            }
+            None => Self::on_chain(s),
+        }
+    }
+}
+
+impl std::str::FromStr for PaymentMethodId {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(s.into())
+    }
+}
+
+impl std::fmt::Display for PaymentMethodId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.payment_type {
+            PaymentType::BTCLike => write!(f, "{}", self.crypto_code),
+            ref other => write!(f, "{}_{}", self.crypto_code, other),
+        }
+    }
+}
+
+impl Serialize for PaymentMethodId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for PaymentMethodId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(String::deserialize(deserializer)?.as_str().into())
+    }
+}
+
+/// Values keyed by payment method. Entries that the server sends as `null` are dropped.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PaymentMethods<T>(pub HashMap<PaymentMethodId, T>);
+
+impl<T> PaymentMethods<T> {
+    pub fn get_method(&self, crypto_code: &str, payment_type: PaymentType) -> Option<&T> {
+        self.0.get(&PaymentMethodId::new(crypto_code, payment_type))
+    }
+
+    pub fn on_chain(&self, crypto_code: &str) -> Option<&T> {
+        self.get_method(crypto_code, PaymentType::BTCLike)
+    }
+
+    pub fn lightning(&self, crypto_code: &str) -> Option<&T> {
+        self.get_method(crypto_code, PaymentType::LightningLike)
+    }
+
+    pub fn lnurl_pay(&self, crypto_code: &str) -> Option<&T> {
+        self.get_method(crypto_code, PaymentType::LNURLPay)
+    }
+}
+
+impl<T> Default for PaymentMethods<T> {
+    fn default() -> Self {
+        PaymentMethods(HashMap::new())
+    }
+}
+
+impl<T> Deref for PaymentMethods<T> {
+    type Target = HashMap<PaymentMethodId, T>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for PaymentMethods<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for PaymentMethods<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let map = HashMap::<PaymentMethodId, Option<T>>::deserialize(deserializer)?;
+
+        Ok(PaymentMethods(
+            map.into_iter()
+                .filter_map(|(id, value)| Some((id, value?)))
+                .collect(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_payment_method_id() {
+        let cases = [
+            ("BTC", "BTC", PaymentType::BTCLike),
+            ("BTC_LightningLike", "BTC", PaymentType::LightningLike),
+            ("BTC_LNURLPAY", "BTC", PaymentType::LNURLPay),
+            ("BTC-CHAIN", "BTC", PaymentType::BTCLike),
+            ("BTC-LN", "BTC", PaymentType::LightningLike),
+            ("BTC-LightningNetwork", "BTC", PaymentType::LightningLike),
+            ("LTC-CHAIN", "LTC", PaymentType::BTCLike),
+            ("XMR", "XMR", PaymentType::BTCLike),
+            (
+                "XMR_MoneroLike",
+                "XMR",
+                PaymentType::Unknown("MoneroLike".into()),
+            ),
+        ];
+
+        for (s, crypto_code, payment_type) in cases.iter() {
+            let parsed: PaymentMethodId = s.parse().unwrap();
+            assert_eq!(
+                parsed,
+                PaymentMethodId::new(crypto_code, payment_type.clone())
+            );
+        }
+
+        assert_eq!(
+            PaymentMethodId::lightning("BTC").to_string(),
+            "BTC_LightningLike"
+        );
+        assert_eq!(PaymentMethodId::on_chain("LTC").to_string(), "LTC");
+
+        assert_eq!(
+            PaymentMethodId::on_chain("BTC").to_greenfield_id(),
+            "BTC-CHAIN"
+        );
+        assert_eq!(
+            PaymentMethodId::lightning("BTC").to_greenfield_id(),
+            "BTC-LN"
+        );
+        let lnurl = PaymentMethodId::lnurl_pay("BTC").to_greenfield_id();
+        assert_eq!(lnurl, "BTC-LNURL");
+        assert_eq!(
+            lnurl.parse::<PaymentMethodId>().unwrap(),
+            PaymentMethodId::lnurl_pay("BTC")
+        );
+    }
+}
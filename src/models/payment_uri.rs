@@ -0,0 +1,598 @@
+use std::convert::TryFrom;
+use std::str::FromStr;
+use std::time::Duration;
+
+use bitcoin::address::{Address, NetworkUnchecked};
+use bitcoin::bech32::{self, u5, FromBase32};
+use bitcoin::hashes::sha256::Hash as Sha256Hash;
+use bitcoin::hashes::Hash;
+use bitcoin::{Amount, Denomination, Network};
+
+use super::invoice::{Invoice, PaymentUrl};
+use super::network::detect_address_network;
+use super::payment_method::PaymentMethodId;
+use super::timestamp::{self, DateTime};
+use crate::core::client::Error;
+
+fn strip_scheme<'a>(s: &'a str, scheme: &str) -> &'a str {
+    match s.get(..scheme.len()) {
+        Some(prefix) if prefix.eq_ignore_ascii_case(scheme) => &s[scheme.len()..],
+        _ => s,
+    }
+}
+
+fn percent_encode(s: &str) -> String {
+    s.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// A BIP21 URI, like `bitcoin:<address>?amount=<amount>&lightning=<bolt11>`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bip21Uri {
+    /// Lowercase URI scheme, usually `bitcoin` (or `litecoin` and others for altcoins)
+    pub scheme: String,
+    pub address: String,
+    pub amount: Option<Amount>,
+    pub label: Option<String>,
+    pub message: Option<String>,
+    /// BOLT11 invoice to use as a fallback by wallets that support Lightning
+    pub lightning: Option<String>,
+    /// Other query parameters, in order
+    pub params: Vec<(String, String)>,
+}
+
+impl Bip21Uri {
+    pub fn new(scheme: &str, address: &str) -> Self {
+        Bip21Uri {
+            scheme: scheme.to_lowercase(),
+            address: address.to_string(),
+            amount: None,
+            label: None,
+            message: None,
+            lightning: None,
+            params: Vec::new(),
+        }
+    }
+
+    pub fn parsed_lightning(&self) -> Result<Option<Bolt11Invoice>, Error> {
+        self.lightning.as_deref().map(str::parse).transpose()
+    }
+}
+
+impl FromStr for Bip21Uri {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || Error::InvalidPaymentUri(s.to_string());
+
+        let (scheme, rest) = s.split_once(':').ok_or_else(invalid)?;
+        let (address, query) = match rest.split_once('?') {
+            Some((address, query)) => (address, query),
+            None => (rest, ""),
+        };
+        if address.is_empty() {
+            return Err(invalid());
+        }
+
+        let mut uri = Bip21Uri::new(scheme, address);
+        let pairs: Vec<(String, String)> =
+            serde_urlencoded::from_str(query).map_err(|_| invalid())?;
+        for (key, value) in pairs {
+            match key.to_lowercase().as_str() {
+                "amount" => {
+                    uri.amount = Some(
+                        Amount::from_str_in(&value, Denomination::Bitcoin)
+                            .map_err(|_| invalid())?,
+                    )
+                }
+                "label" => uri.label = Some(value),
+                "message" => uri.message = Some(value),
+                "lightning" => uri.lightning = Some(value),
+                // Unknown required parameters mean we can't safely handle the payment
+                k if k.starts_with("req-") => return Err(invalid()),
+                _ => uri.params.push((key, value)),
+            }
+        }
+
+        Ok(uri)
+    }
+}
+
+impl std::fmt::Display for Bip21Uri {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.scheme, self.address)?;
+
+        let mut params = Vec::new();
+        if let Some(amount) = self.amount {
+            params.push(format!(
+                "amount={}",
+                amount.to_string_in(Denomination::Bitcoin)
+            ));
+        }
+        if let Some(label) = &self.label {
+            params.push(format!("label={}", percent_encode(label)));
+        }
+        if let Some(message) = &self.message {
+            params.push(format!("message={}", percent_encode(message)));
+        }
+        for (key, value) in &self.params {
+            params.push(format!("{}={}", percent_encode(key), percent_encode(value)));
+        }
+        if let Some(lightning) = &self.lightning {
+            params.push(format!("lightning={}", lightning));
+        }
+
+        if !params.is_empty() {
+            write!(f, "?{}", params.join("&"))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The fields of a BOLT11 Lightning invoice that matter when displaying it. The signature is not
+/// verified.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bolt11Invoice {
+    /// The invoice, lowercase and without the `lightning:` prefix
+    pub invoice: String,
+    /// Network prefix: `bc`, `tb`, `tbs` or `bcrt`
+    pub currency: String,
+    pub amount_msat: Option<u64>,
+    /// Creation time, in seconds since the unix epoch
+    pub timestamp: u64,
+    pub expiry: Duration,
+    pub payment_hash: Sha256Hash,
+    pub description: Option<String>,
+}
+
+impl Bolt11Invoice {
+    pub fn amount(&self) -> Option<Amount> {
+        self.amount_msat.map(|msat| Amount::from_sat(msat / 1000))
+    }
+
+    pub fn network(&self) -> Option<Network> {
+        match self.currency.as_str() {
+            "bc" => Some(Network::Bitcoin),
+            "tb" => Some(Network::Testnet),
+            "tbs" => Some(Network::Signet),
+            "bcrt" => Some(Network::Regtest),
+            _ => None,
+        }
+    }
+
+    pub fn created_at(&self) -> DateTime {
+        timestamp::from_millis(self.timestamp as i64 * 1000)
+    }
+
+    /// `None` if the expiry is too far in the future to be represented
+    pub fn expires_at(&self) -> Option<DateTime> {
+        let seconds = self.timestamp.checked_add(self.expiry.as_secs())?;
+
+        i64::try_from(seconds)
+            .ok()?
+            .checked_mul(1000)
+            .and_then(timestamp::checked_from_millis)
+    }
+
+    // `u64::is_multiple_of` is only available since Rust 1.87
+    #[allow(clippy::manual_is_multiple_of)]
+    fn parse_amount(amount: &str) -> Option<u64> {
+        let (digits, multiplier) = match amount.char_indices().last()? {
+            (i, c) if c.is_ascii_alphabetic() => (&amount[..i], Some(c)),
+            _ => (amount, None),
+        };
+        let value: u64 = digits.parse().ok()?;
+
+        match multiplier {
+            None => value.checked_mul(100_000_000_000),
+            Some('m') => value.checked_mul(100_000_000),
+            Some('u') => value.checked_mul(100_000),
+            Some('n') => value.checked_mul(100),
+            Some('p') if value % 10 == 0 => Some(value / 10),
+            _ => None,
+        }
+    }
+
+    /// `None` if `data` doesn't fit in 64 bits
+    fn read_int(data: &[u5]) -> Option<u64> {
+        if data.len() > 12 {
+            return None;
+        }
+
+        Some(
+            data.iter()
+                .fold(0, |acc, value| (acc << 5) | value.to_u8() as u64),
+        )
+    }
+}
+
+impl FromStr for Bolt11Invoice {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || Error::InvalidPaymentUri(s.to_string());
+
+        let invoice = strip_scheme(s, "lightning:").to_lowercase();
+        let (hrp, data, _) = bech32::decode(&invoice).map_err(|_| invalid())?;
+
+        let hrp = hrp.strip_prefix("ln").ok_or_else(invalid)?;
+        let split = hrp.find(|c: char| c.is_ascii_digit()).unwrap_or(hrp.len());
+        let (currency, amount) = hrp.split_at(split);
+        let amount_msat = match amount {
+            "" => None,
+            amount => Some(Self::parse_amount(amount).ok_or_else(invalid)?),
+        };
+
+        // Timestamp, then tagged fields, then a 65 bytes signature
+        const TIMESTAMP_LEN: usize = 7;
+        const SIGNATURE_LEN: usize = 104;
+        if data.len() < TIMESTAMP_LEN + SIGNATURE_LEN {
+            return Err(invalid());
+        }
+        let timestamp = Self::read_int(&data[..TIMESTAMP_LEN]).ok_or_else(invalid)?;
+        let mut fields = &data[TIMESTAMP_LEN..data.len() - SIGNATURE_LEN];
+
+        let mut payment_hash = None;
+        let mut description = None;
+        let mut expiry = Duration::from_secs(3600);
+        while fields.len() >= 3 {
+            let tag = fields[0].to_u8();
+            let len = Self::read_int(&fields[1..3]).ok_or_else(invalid)? as usize;
+            let value = fields.get(3..3 + len).ok_or_else(invalid)?;
+            fields = &fields[3 + len..];
+
+            match tag {
+                // 'p'
+                1 if len == 52 => {
+                    let bytes = Vec::<u8>::from_base32(value).map_err(|_| invalid())?;
+                    payment_hash = Some(Sha256Hash::from_slice(&bytes).map_err(|_| invalid())?);
+                }
+                // 'd'
+                13 => {
+                    let bytes = Vec::<u8>::from_base32(value).map_err(|_| invalid())?;
+                    description = Some(String::from_utf8(bytes).map_err(|_| invalid())?);
+                }
+                // 'x'
+                6 => expiry = Duration::from_secs(Self::read_int(value).ok_or_else(invalid)?),
+                _ => {}
+            }
+        }
+
+        Ok(Bolt11Invoice {
+            invoice,
+            currency: currency.to_string(),
+            amount_msat,
+            timestamp,
+            expiry,
+            payment_hash: payment_hash.ok_or_else(invalid)?,
+            description,
+        })
+    }
+}
+
+impl std::fmt::Display for Bolt11Invoice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "lightning:{}", self.invoice)
+    }
+}
+
+/// A bech32-encoded LNURL, decoded to the URL it points to
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lnurl {
+    /// The LNURL, lowercase and without the `lightning:` prefix
+    pub lnurl: String,
+    pub url: String,
+}
+
+impl FromStr for Lnurl {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || Error::InvalidPaymentUri(s.to_string());
+
+        let lnurl = strip_scheme(s, "lightning:").to_lowercase();
+        let (hrp, data, _) = bech32::decode(&lnurl).map_err(|_| invalid())?;
+        if hrp != "lnurl" {
+            return Err(invalid());
+        }
+
+        let url = String::from_utf8(Vec::<u8>::from_base32(&data).map_err(|_| invalid())?)
+            .map_err(|_| invalid())?;
+
+        Ok(Lnurl { lnurl, url })
+    }
+}
+
+impl std::fmt::Display for Lnurl {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "lightning:{}", self.lnurl)
+    }
+}
+
+impl PaymentUrl {
+    pub fn parse_bip21(&self) -> Result<Option<Bip21Uri>, Error> {
+        self.bip21.as_deref().map(str::parse).transpose()
+    }
+
+    pub fn parse_bolt11(&self) -> Result<Option<Bolt11Invoice>, Error> {
+        self.bolt11.as_deref().map(str::parse).transpose()
+    }
+
+    pub fn parse_lnurlp(&self) -> Result<Option<Lnurl>, Error> {
+        self.lnurlp.as_deref().map(str::parse).transpose()
+    }
+}
+
+impl Invoice {
+    /// Builds a single BIP21 URI paying the BTC on-chain address, with the Lightning invoice (or
+    /// LNURL, if there is no invoice) as the `lightning=` fallback.
+    ///
+    /// Returns `None` if the invoice has no BTC on-chain payment method, and an error if the
+    /// on-chain and Lightning parts disagree on the amount or the network.
+    pub fn unified_payment_uri(&self) -> Result<Option<Bip21Uri>, Error> {
+        let on_chain = match self
+            .crypto_info
+            .iter()
+            .find(|info| info.payment_method_id() == PaymentMethodId::on_chain("BTC"))
+        {
+            Some(info) => info,
+            None => return Ok(None),
+        };
+        let address = match &on_chain.address {
+            Some(address) => address,
+            None => return Ok(None),
+        };
+        let parse_btc = |amount: &str| {
+            Amount::from_str_in(amount, Denomination::Bitcoin).map_err(|_| Error::InvalidResponse)
+        };
+        let due = parse_btc(&on_chain.due)?;
+        // Only on-chain payments pay a network fee, Lightning invoices are for the rest
+        let lightning_due = due
+            .checked_sub(parse_btc(&on_chain.network_fee)?)
+            .ok_or(Error::InvalidResponse)?;
+        let parsed_address: Address<NetworkUnchecked> = address
+            .parse()
+            .map_err(|_| Error::InvalidAddress(address.clone()))?;
+
+        let mut uri = Bip21Uri::new("bitcoin", address);
+        uri.amount = Some(due);
+
+        let mismatch = |reason: &str, lightning: &str| {
+            Error::InconsistentPaymentMethods(format!(
+                "{} for on-chain address {} and Lightning {}",
+                reason, address, lightning
+            ))
+        };
+
+        let bolt11 = self
+            .addresses
+            .lightning("BTC")
+            .map(|invoice| invoice.parse::<Bolt11Invoice>())
+            .transpose()?;
+        if let Some(bolt11) = bolt11 {
+            // The server rounds the invoice amount to the millisatoshi
+            if let Some(amount_msat) = bolt11.amount_msat {
+                if amount_msat.abs_diff(lightning_due.to_sat() * 1000) >= 1000 {
+                    return Err(mismatch("different amounts", &bolt11.invoice));
+                }
+            }
+
+            match bolt11.network() {
+                Some(network) if parsed_address.is_valid_for_network(network) => {}
+                _ => return Err(mismatch("different networks", &bolt11.invoice)),
+            }
+
+            uri.lightning = Some(bolt11.invoice);
+        } else if let Some(lnurl) = self.payment_codes.lnurl_pay("BTC") {
+            if let Some(lnurl) = lnurl.parse_lnurlp()? {
+                // An LNURL doesn't tell its network, so the address has to agree with the other
+                // BTC destinations of the invoice instead
+                let network = detect_address_network(address)
+                    .ok_or_else(|| Error::InvalidAddress(address.clone()))?;
+                if let Err(Error::NetworkMismatch(..)) = self.check_network(network) {
+                    return Err(mismatch("different networks", &lnurl.lnurl));
+                }
+
+                uri.lightning = Some(lnurl.lnurl.to_uppercase());
+            }
+        }
+
+        Ok(Some(uri))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const BOLT11: &str = "lightning:lntb37250n1pjt27akpp5dh0yfefyvek68frjl886hnxwcrkwvrkd9cdnmke8j0l4fpqf2fxqdpj2pskjepqw3hjq4r9wd6zqum5daex2gpgfaexgetjypy5gw3q9ycqzzsxqzuysp5ealwkddt9phg7vsqu7k9xh373r0mcjc6kktwjmfnrhlkmk64tmyq9qyyssqwaazrhey5kmy09j83u2x6wsvjcqs8cwrz798ka5lfzfws8w5u2s5a32yavtkw6n6hj8zpk84np7kfttvuwmjc0tgpxvdq2ak20jsevcq3ujhlp";
+    const LNURL: &str = "lightning:lnurl1dp68gurn8ghj7ar9wd6xuet59ejx2mt09e38gcmsv9uhxetjwejhytn0wfnj7sj5gvh42j2vfe24ynp0wpshjtmf9umhzvj5fpvkgvnegf2hz4mex3p8we2k2a4x2s0vlde";
+
+    #[test]
+    fn test_parse_bip21() {
+        let uri: Bip21Uri =
+            "bitcoin:tb1qxzv66jwlm5pphum53alctrsdr7stfahc6hnxeg?amount=0.0026093&label=Shop%20order&foo=bar"
+                .parse()
+                .unwrap();
+
+        assert_eq!(uri.scheme, "bitcoin");
+        assert_eq!(uri.address, "tb1qxzv66jwlm5pphum53alctrsdr7stfahc6hnxeg");
+        assert_eq!(uri.amount, Some(Amount::from_sat(260930)));
+        assert_eq!(uri.label.as_deref(), Some("Shop order"));
+        assert_eq!(uri.params, vec![("foo".to_string(), "bar".to_string())]);
+        assert_eq!(uri.to_string().parse::<Bip21Uri>().unwrap(), uri);
+
+        assert!("bitcoin:tb1q?req-unknown=1".parse::<Bip21Uri>().is_err());
+        assert!("bitcoin:tb1q?amount=abc".parse::<Bip21Uri>().is_err());
+    }
+
+    #[test]
+    fn test_parse_bolt11() {
+        let invoice: Bolt11Invoice = BOLT11.parse().unwrap();
+
+        assert_eq!(invoice.currency, "tb");
+        assert_eq!(invoice.amount(), Some(Amount::from_sat(3725)));
+        assert_eq!(invoice.timestamp, 1689615286);
+        assert_eq!(invoice.expiry, Duration::from_secs(900));
+        assert_eq!(
+            invoice.payment_hash.to_string(),
+            "6dde44e524666da3a472f9cfabcccec0ece60ecd2e1b3ddb2793ff548409524c"
+        );
+        assert_eq!(invoice.to_string(), BOLT11);
+        assert_eq!(
+            timestamp::to_millis(&invoice.expires_at().unwrap()),
+            1689616186000
+        );
+    }
+
+    /// Encodes an invoice with a zero payment hash, the expiry given as 5 bits groups and a zero
+    /// signature
+    fn encode_bolt11(hrp: &str, timestamp: u64, expiry: &[u8]) -> String {
+        let u5 = |value| u5::try_from_u8(value).unwrap();
+        let mut data: Vec<u5> = (0..7)
+            .rev()
+            .map(|i| u5((timestamp >> (i * 5)) as u8 & 31))
+            .collect();
+        data.extend([1, 52 / 32, 52 % 32].iter().map(|&v| u5(v)));
+        data.extend(vec![u5(0); 52]);
+        data.extend([6, 0, expiry.len() as u8].iter().map(|&v| u5(v)));
+        data.extend(expiry.iter().map(|&v| u5(v)));
+        data.extend(vec![u5(0); 104]);
+
+        bech32::encode(hrp, data, bech32::Variant::Bech32).unwrap()
+    }
+
+    #[test]
+    fn test_bolt11_expiry_bounds() {
+        let invoice: Bolt11Invoice = encode_bolt11("lntb", 1689615286, &[28, 4]).parse().unwrap();
+        assert_eq!(invoice.expiry, Duration::from_secs(900));
+
+        // The largest expiry that fits in 12 groups overflows the timestamp
+        let invoice: Bolt11Invoice = encode_bolt11("lntb", u32::MAX as u64, &[31; 12])
+            .parse()
+            .unwrap();
+        assert_eq!(invoice.expiry.as_secs(), (1 << 60) - 1);
+        assert_eq!(invoice.expires_at(), None);
+
+        assert!(matches!(
+            encode_bolt11("lntb", 1689615286, &[31; 13]).parse::<Bolt11Invoice>(),
+            Err(Error::InvalidPaymentUri(_))
+        ));
+    }
+
+    fn get_test_unified_json(amount: &str, network_fee: &str) -> serde_json::Value {
+        let mut json: serde_json::Value =
+            serde_json::from_str(crate::models::invoice::test::get_test_invoices_json()[0])
+                .unwrap();
+        let mut on_chain = json["cryptoInfo"][1].clone();
+        on_chain["paymentType"] = "BTCLike".into();
+        on_chain["address"] = "tb1qxzv66jwlm5pphum53alctrsdr7stfahc6hnxeg".into();
+        on_chain["due"] = amount.into();
+        on_chain["networkFee"] = network_fee.into();
+        json["cryptoInfo"].as_array_mut().unwrap().push(on_chain);
+
+        json
+    }
+
+    fn get_test_unified_invoice(amount: &str) -> Invoice {
+        serde_json::from_value(get_test_unified_json(amount, "0.00000000")).unwrap()
+    }
+
+    #[test]
+    fn test_unified_payment_uri() {
+        let uri = get_test_unified_invoice("0.00003725")
+            .unified_payment_uri()
+            .unwrap()
+            .unwrap();
+        assert_eq!(uri.address, "tb1qxzv66jwlm5pphum53alctrsdr7stfahc6hnxeg");
+        assert_eq!(uri.amount, Some(Amount::from_sat(3725)));
+        assert_eq!(
+            uri.parsed_lightning().unwrap().unwrap().amount(),
+            Some(Amount::from_sat(3725))
+        );
+
+        assert!(matches!(
+            get_test_unified_invoice("0.001").unified_payment_uri(),
+            Err(Error::InconsistentPaymentMethods(_))
+        ));
+
+        let lightning_only: Invoice =
+            serde_json::from_str(crate::models::invoice::test::get_test_invoices_json()[0])
+                .unwrap();
+        assert_eq!(lightning_only.unified_payment_uri().unwrap(), None);
+    }
+
+    #[test]
+    fn test_unified_payment_uri_network_fee() {
+        let invoice: Invoice =
+            serde_json::from_value(get_test_unified_json("0.00004725", "0.00001000")).unwrap();
+        let uri = invoice.unified_payment_uri().unwrap().unwrap();
+        assert_eq!(uri.amount, Some(Amount::from_sat(4725)));
+        assert_eq!(
+            uri.parsed_lightning().unwrap().unwrap().amount(),
+            Some(Amount::from_sat(3725))
+        );
+
+        // Amounts in millisatoshis are rounded
+        let mut json = get_test_unified_json("0.00003725", "0.00000000");
+        json["addresses"]["BTC_LightningLike"] =
+            encode_bolt11("lntb37254300p", 1689615286, &[28, 4]).into();
+        let invoice: Invoice = serde_json::from_value(json).unwrap();
+        invoice.unified_payment_uri().unwrap().unwrap();
+
+        let mut json = get_test_unified_json("0.00003726", "0.00000000");
+        json["addresses"]["BTC_LightningLike"] =
+            encode_bolt11("lntb37250n", 1689615286, &[28, 4]).into();
+        let invoice: Invoice = serde_json::from_value(json).unwrap();
+        assert!(matches!(
+            invoice.unified_payment_uri(),
+            Err(Error::InconsistentPaymentMethods(_))
+        ));
+    }
+
+    #[test]
+    fn test_unified_payment_uri_lnurl() {
+        let mut json = get_test_unified_json("0.00003725", "0.00000000");
+        json["addresses"]
+            .as_object_mut()
+            .unwrap()
+            .remove("BTC_LightningLike");
+        json["cryptoInfo"].as_array_mut().unwrap().remove(1);
+
+        let invoice: Invoice = serde_json::from_value(json.clone()).unwrap();
+        let uri = invoice.unified_payment_uri().unwrap().unwrap();
+        assert_eq!(
+            uri.lightning.as_deref(),
+            Some(
+                LNURL
+                    .trim_start_matches("lightning:")
+                    .to_uppercase()
+                    .as_str()
+            )
+        );
+
+        json["bitcoinAddress"] = "bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq".into();
+        let invoice: Invoice = serde_json::from_value(json).unwrap();
+        assert!(matches!(
+            invoice.unified_payment_uri(),
+            Err(Error::InconsistentPaymentMethods(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_lnurl() {
+        let lnurl: Lnurl = LNURL.parse().unwrap();
+
+        assert!(lnurl
+            .url
+            .starts_with("https://testnet.demo.btcpayserver.org/"));
+        assert_eq!(lnurl.to_string(), LNURL);
+    }
+}
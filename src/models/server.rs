@@ -0,0 +1,114 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::payment_method::PaymentMethodId;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HealthCheck {
+    /// Whether every chain supported by the server is synchronized
+    pub synchronized: bool,
+}
+
+/// State of the node backing a chain
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeInformation {
+    pub headers: u64,
+    pub blocks: u64,
+    /// Between 0 and 1
+    pub verification_progress: f64,
+}
+
+/// Synchronization state of a chain
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncStatus {
+    /// Sent by older servers, newer ones identify the chain with `payment_method_id`
+    pub crypto_code: Option<String>,
+    pub payment_method_id: Option<PaymentMethodId>,
+    pub node_information: Option<NodeInformation>,
+    /// Height of the chain, as seen by the node
+    pub chain_height: u64,
+    /// Height the server has processed up to
+    pub sync_height: Option<u64>,
+    pub available: bool,
+}
+
+impl SyncStatus {
+    pub fn get_crypto_code(&self) -> Option<&str> {
+        self.crypto_code.as_deref().or_else(|| {
+            self.payment_method_id
+                .as_ref()
+                .map(|p| p.crypto_code.as_str())
+        })
+    }
+
+    /// The node is reachable and the server has processed the whole chain
+    pub fn is_synced(&self) -> bool {
+        self.available
+            && self
+                .sync_height
+                .is_some_and(|height| height >= self.chain_height)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServerInfo {
+    pub version: String,
+    /// Tor onion address of the server, if exposed
+    pub onion: Option<String>,
+    #[serde(default)]
+    pub supported_payment_methods: Vec<PaymentMethodId>,
+    pub fully_synched: bool,
+    #[serde(default)]
+    pub sync_status: Vec<SyncStatus>,
+
+    /// Fields not known to this version of the library
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
+}
+
+impl ServerInfo {
+    pub fn get_sync_status(&self, crypto_code: &str) -> Option<&SyncStatus> {
+        self.sync_status
+            .iter()
+            .find(|s| s.get_crypto_code() == Some(crypto_code))
+    }
+
+    /// Whether invoices can be paid with `payment_method`: the server supports it and its chain is
+    /// synchronized.
+    ///
+    /// For Lightning methods only the chain of the same crypto code is checked. The server info
+    /// doesn't report the state of Lightning nodes, which are configured per store: a store whose
+    /// node is down still passes.
+    pub fn is_ready_for(&self, payment_method: &PaymentMethodId) -> bool {
+        self.supported_payment_methods.contains(payment_method)
+            && self
+                .get_sync_status(&payment_method.crypto_code)
+                .is_some_and(SyncStatus::is_synced)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+
+    pub const SERVER_INFO_JSON: &str = r#"{"version":"1.11.7","onion":"btcpayxyzexamplemfq6ov7kqc3wvcwy2zv4bhqkhcsk5wmxu5fd4rqd.onion","supportedPaymentMethods":["BTC","BTC_LightningLike","LTC"],"fullySynched":false,"syncStatus":[{"cryptoCode":"BTC","nodeInformation":{"headers":800100,"blocks":800100,"verificationProgress":0.9999987},"chainHeight":800100,"syncHeight":800100,"available":true},{"paymentMethodId":"LTC-CHAIN","nodeInformation":{"headers":2500000,"blocks":2400000,"verificationProgress":0.93},"chainHeight":2400000,"syncHeight":2399000,"available":true}]}"#;
+
+    #[test]
+    fn test_is_ready_for() {
+        let info: ServerInfo = serde_json::from_str(SERVER_INFO_JSON).unwrap();
+
+        assert!(info.is_ready_for(&PaymentMethodId::on_chain("BTC")));
+        assert!(info.is_ready_for(&PaymentMethodId::lightning("BTC")));
+        assert!(!info.is_ready_for(&PaymentMethodId::lnurl_pay("BTC")));
+        assert!(!info.is_ready_for(&PaymentMethodId::on_chain("LTC")));
+        assert!(!info.is_ready_for(&PaymentMethodId::on_chain("XMR")));
+
+        let ltc = info.get_sync_status("LTC").unwrap();
+        assert_eq!(ltc.node_information.as_ref().unwrap().blocks, 2400000);
+    }
+}
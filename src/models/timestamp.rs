@@ -0,0 +1,299 @@
+//! UTC timestamps used across the models.
+//!
+//! [`DateTime`] is always `std::time::SystemTime`. The `chrono` and `time` features add
+//! conversions to and from their own types.
+
+pub type DateTime = std::time::SystemTime;
+
+pub fn checked_from_millis(millis: i64) -> Option<DateTime> {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    if millis >= 0 {
+        UNIX_EPOCH.checked_add(Duration::from_millis(millis as u64))
+    } else {
+        UNIX_EPOCH.checked_sub(Duration::from_millis(millis.unsigned_abs()))
+    }
+}
+
+pub fn to_millis(timestamp: &DateTime) -> i64 {
+    use std::time::UNIX_EPOCH;
+
+    match timestamp.duration_since(UNIX_EPOCH) {
+        Ok(after) => after.as_millis() as i64,
+        Err(before) => -(before.duration().as_millis() as i64),
+    }
+}
+
+pub fn now() -> DateTime {
+    std::time::SystemTime::now()
+}
+
+#[cfg(feature = "chrono")]
+pub fn to_chrono(timestamp: &DateTime) -> chrono::DateTime<chrono::Utc> {
+    (*timestamp).into()
+}
+
+#[cfg(feature = "chrono")]
+pub fn from_chrono(timestamp: &chrono::DateTime<chrono::Utc>) -> DateTime {
+    (*timestamp).into()
+}
+
+#[cfg(feature = "time")]
+pub fn to_offset_datetime(timestamp: &DateTime) -> time::OffsetDateTime {
+    (*timestamp).into()
+}
+
+#[cfg(feature = "time")]
+pub fn from_offset_datetime(timestamp: &time::OffsetDateTime) -> DateTime {
+    (*timestamp).into()
+}
+
+/// Panics if `millis` can't be represented by [`DateTime`]
+pub fn from_millis(millis: i64) -> DateTime {
+    checked_from_millis(millis).expect("timestamp out of range")
+}
+
+/// Formats `timestamp` as RFC 3339 in UTC, with second precision
+pub fn to_rfc3339(timestamp: &DateTime) -> String {
+    let seconds = to_millis(timestamp).div_euclid(1000);
+    let (days, seconds) = (seconds.div_euclid(86400), seconds.rem_euclid(86400));
+    let (year, month, day) = civil_from_days(days);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+/// Parses an RFC 3339 timestamp such as the ones produced by [`to_rfc3339`]. Fractions of a
+/// second are truncated to milliseconds, and offsets other than `Z` are supported.
+pub fn from_rfc3339(s: &str) -> Option<DateTime> {
+    let number = |range: std::ops::Range<usize>| -> Option<i64> {
+        let digits = s.get(range)?;
+        if digits.bytes().all(|b| b.is_ascii_digit()) {
+            digits.parse().ok()
+        } else {
+            None
+        }
+    };
+    let separators = [(4, b'-'), (7, b'-'), (13, b':'), (16, b':')];
+    if s.len() < 20
+        || separators.iter().any(|&(i, c)| s.as_bytes()[i] != c)
+        || !matches!(s.as_bytes()[10], b'T' | b't' | b' ')
+    {
+        return None;
+    }
+
+    let (year, month, day) = (number(0..4)?, number(5..7)?, number(8..10)?);
+    let (hour, minute, second) = (number(11..13)?, number(14..16)?, number(17..19)?);
+    if !(1..=12).contains(&month)
+        || !(1..=31).contains(&day)
+        || hour > 23
+        || minute > 59
+        || second > 60
+    {
+        return None;
+    }
+
+    let mut rest = &s[19..];
+    let mut millis = 0;
+    if let Some(fraction) = rest.strip_prefix('.') {
+        let len = fraction.bytes().take_while(|b| b.is_ascii_digit()).count();
+        if len == 0 {
+            return None;
+        }
+        let digits = format!("{:0<3}", &fraction[..len.min(3)]);
+        millis = digits.parse::<i64>().ok()?;
+        rest = &fraction[len..];
+    }
+
+    let offset = match rest.as_bytes() {
+        [b'Z'] | [b'z'] => 0,
+        [sign @ (b'+' | b'-'), h1, h2, b':', m1, m2]
+            if [h1, h2, m1, m2].iter().all(|b| b.is_ascii_digit()) =>
+        {
+            let hours = ((h1 - b'0') * 10 + (h2 - b'0')) as i64;
+            let minutes = ((m1 - b'0') * 10 + (m2 - b'0')) as i64;
+            if hours > 23 || minutes > 59 {
+                return None;
+            }
+            let offset = hours * 3600 + minutes * 60;
+            if *sign == b'-' {
+                -offset
+            } else {
+                offset
+            }
+        }
+        _ => return None,
+    };
+
+    let seconds = days_from_civil(year, month as u32, day as u32) * 86400
+        + hour * 3600
+        + minute * 60
+        + second
+        - offset;
+    checked_from_millis(seconds * 1000 + millis)
+}
+
+// http://howardhinnant.github.io/date_algorithms.html#days_from_civil
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = (month as i64 + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+
+    era * 146097 + doe - 719468
+}
+
+// http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+
+    (yoe + era * 400 + (month <= 2) as i64, month, day)
+}
+
+/// (De)serializes a [`DateTime`] as milliseconds since the unix epoch
+pub mod millis {
+    use serde::de::Error as _;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        timestamp: &DateTime,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_i64(to_millis(timestamp))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<DateTime, D::Error> {
+        let millis = i64::deserialize(deserializer)?;
+
+        checked_from_millis(millis)
+            .ok_or_else(|| D::Error::custom(format!("timestamp out of range: {}", millis)))
+    }
+}
+
+/// (De)serializes a [`DateTime`] as seconds since the unix epoch, as used by the Greenfield API
+pub mod seconds {
+    use serde::de::Error as _;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        timestamp: &DateTime,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_i64(to_millis(timestamp).div_euclid(1000))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<DateTime, D::Error> {
+        let seconds = i64::deserialize(deserializer)?;
+
+        seconds
+            .checked_mul(1000)
+            .and_then(checked_from_millis)
+            .ok_or_else(|| D::Error::custom(format!("timestamp out of range: {}", seconds)))
+    }
+
+    /// Same as [`seconds`](super), for optional timestamps sent as `null` when unset
+    pub mod option {
+        use serde::{Deserialize, Deserializer, Serializer};
+
+        use super::super::DateTime;
+
+        pub fn serialize<S: Serializer>(
+            timestamp: &Option<DateTime>,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            match timestamp {
+                Some(timestamp) => super::serialize(timestamp, serializer),
+                None => serializer.serialize_none(),
+            }
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<Option<DateTime>, D::Error> {
+            #[derive(Deserialize)]
+            struct Wrapper(#[serde(with = "super")] DateTime);
+
+            Ok(Option::<Wrapper>::deserialize(deserializer)?.map(|Wrapper(t)| t))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_millis_roundtrip() {
+        assert_eq!(to_millis(&from_millis(1689615287973)), 1689615287973);
+        assert_eq!(to_millis(&from_millis(-1500)), -1500);
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_chrono() {
+        let timestamp = from_millis(1689615287973);
+        let converted = to_chrono(&timestamp);
+
+        assert_eq!(converted.timestamp_millis(), 1689615287973);
+        assert_eq!(from_chrono(&converted), timestamp);
+    }
+
+    #[cfg(feature = "time")]
+    #[test]
+    fn test_time() {
+        let timestamp = from_millis(1689615287973);
+        let converted = to_offset_datetime(&timestamp);
+
+        assert_eq!(converted.unix_timestamp(), 1689615287);
+        assert_eq!(from_offset_datetime(&converted), timestamp);
+    }
+
+    #[test]
+    fn test_to_rfc3339() {
+        assert_eq!(
+            to_rfc3339(&from_millis(1587907315000)),
+            "2020-04-26T13:21:55Z"
+        );
+        assert_eq!(
+            to_rfc3339(&from_millis(951782400000)),
+            "2000-02-29T00:00:00Z"
+        );
+        assert_eq!(to_rfc3339(&from_millis(-1000)), "1969-12-31T23:59:59Z");
+    }
+
+    #[test]
+    fn test_from_rfc3339() {
+        for millis in [1587907315000, 951782400000, -1000, 0] {
+            let timestamp = from_rfc3339(&to_rfc3339(&from_millis(millis))).unwrap();
+            assert_eq!(to_millis(&timestamp), millis);
+        }
+
+        let parse = |s| from_rfc3339(s).map(|t| to_millis(&t));
+        assert_eq!(parse("2020-04-26T15:21:55.1234+02:00"), Some(1587907315123));
+        assert_eq!(parse("2020-04-26t13:21:55z"), Some(1587907315000));
+        assert_eq!(parse("2020-04-26T13:21:55"), None);
+        assert_eq!(parse("2020-13-26T13:21:55Z"), None);
+        assert_eq!(parse("2020-04-26T13:21:55.Z"), None);
+        assert_eq!(parse("not a timestamp"), None);
+    }
+}
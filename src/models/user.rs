@@ -0,0 +1,118 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::timestamp::{self, DateTime};
+
+string_enum! {
+    /// Server-wide role of a user, as opposed to its role in each store
+    pub enum ServerRole {
+        ServerAdmin => "ServerAdmin",
+    }
+}
+
+string_enum! {
+    pub enum StoreRole {
+        Owner => "Owner",
+        Manager => "Manager",
+        Employee => "Employee",
+        Guest => "Guest",
+    }
+}
+
+/// Body to create a user
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateUserRequest {
+    pub email: String,
+    /// Without a password the user has to set one through the invitation email
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub is_administrator: bool,
+}
+
+impl CreateUserRequest {
+    /// A regular user, which is not an administrator
+    pub fn new(email: &str, password: &str) -> Self {
+        CreateUserRequest {
+            email: email.to_string(),
+            password: Some(password.to_string()),
+            name: None,
+            is_administrator: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApplicationUser {
+    pub id: String,
+    pub email: String,
+    pub name: Option<String>,
+    #[serde(default)]
+    pub email_confirmed: bool,
+    #[serde(default)]
+    pub requires_email_confirmation: bool,
+    #[serde(default)]
+    pub approved: bool,
+    #[serde(default)]
+    pub requires_approval: bool,
+    /// Locked out of the server, see `lock_user`
+    #[serde(default)]
+    pub disabled: bool,
+    #[serde(with = "timestamp::seconds::option", default)]
+    pub created: Option<DateTime>,
+    #[serde(default)]
+    pub roles: Vec<ServerRole>,
+
+    /// Fields not known to this version of the library
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
+}
+
+impl ApplicationUser {
+    pub fn is_admin(&self) -> bool {
+        self.roles.contains(&ServerRole::ServerAdmin)
+    }
+}
+
+/// User with access to a store
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StoreUser {
+    pub user_id: String,
+    pub role: StoreRole,
+    /// Only sent by newer servers
+    pub email: Option<String>,
+
+    /// Fields not known to this version of the library
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+
+    pub const USER_JSON: &str = r#"{"id":"7b2a9c1e-5d3f-4e8a-9b6c-2f1d0e3a4b5c","email":"merchant@example.com","name":null,"emailConfirmed":true,"requiresEmailConfirmation":false,"approved":true,"requiresApproval":false,"created":1689615287,"roles":["ServerAdmin"],"disabled":false}"#;
+
+    #[test]
+    fn test_parse_user() {
+        let user: ApplicationUser = serde_json::from_str(USER_JSON).unwrap();
+
+        assert!(user.is_admin());
+        assert_eq!(user.roles, vec![ServerRole::ServerAdmin]);
+        assert_eq!(timestamp::to_millis(&user.created.unwrap()), 1689615287000);
+        assert!(!user.disabled);
+
+        let user: ApplicationUser = serde_json::from_str(
+            r#"{"id":"a","email":"b@example.com","created":null,"roles":["Auditor"]}"#,
+        )
+        .unwrap();
+        assert!(!user.is_admin());
+        assert_eq!(user.roles, vec![ServerRole::Unknown("Auditor".into())]);
+        assert!(user.created.is_none());
+    }
+}
@@ -0,0 +1,155 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::invoice::Invoice;
+use super::timestamp::{self, DateTime};
+
+string_enum! {
+    pub enum WebhookEventType {
+        InvoiceCreated => "InvoiceCreated",
+        InvoiceReceivedPayment => "InvoiceReceivedPayment",
+        InvoicePaymentSettled => "InvoicePaymentSettled",
+        InvoiceProcessing => "InvoiceProcessing",
+        InvoiceExpired => "InvoiceExpired",
+        InvoiceSettled => "InvoiceSettled",
+        InvoiceInvalid => "InvoiceInvalid",
+    }
+}
+
+/// Body of a Greenfield webhook delivery
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookPayload {
+    pub delivery_id: String,
+    pub webhook_id: String,
+    /// Id of the first delivery of this event, when this one is a redelivery
+    pub original_delivery_id: Option<String>,
+    #[serde(default)]
+    pub is_redelivery: bool,
+    #[serde(rename = "type")]
+    pub event_type: WebhookEventType,
+    #[serde(with = "timestamp::seconds")]
+    pub timestamp: DateTime,
+    pub store_id: String,
+    pub invoice_id: Option<String>,
+
+    /// Fields specific to the event type, like `payment` or `manuallyMarked`
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
+}
+
+/// A verified notification received from the server
+#[derive(Debug, Clone)]
+pub enum WebhookEvent {
+    Webhook(Box<WebhookPayload>),
+    /// Legacy IPN, carrying the invoice as fetched back from the server
+    Ipn(Box<Invoice>),
+}
+
+/// Events a webhook is sent for
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthorizedEvents {
+    pub everything: bool,
+    #[serde(default)]
+    pub specific_events: Vec<WebhookEventType>,
+}
+
+impl AuthorizedEvents {
+    pub fn everything() -> Self {
+        AuthorizedEvents {
+            everything: true,
+            specific_events: Vec::new(),
+        }
+    }
+
+    pub fn only<I: IntoIterator<Item = WebhookEventType>>(events: I) -> Self {
+        AuthorizedEvents {
+            everything: false,
+            specific_events: events.into_iter().collect(),
+        }
+    }
+}
+
+/// Body to create or update a webhook
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookRequest {
+    pub url: String,
+    pub enabled: bool,
+    pub automatic_redelivery: bool,
+    pub authorized_events: AuthorizedEvents,
+    /// Generated by the server on creation if not set, and left unchanged on update
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+}
+
+impl WebhookRequest {
+    /// An enabled webhook sent for every event, with automatic redelivery
+    pub fn new(url: &str) -> Self {
+        WebhookRequest {
+            url: url.to_string(),
+            enabled: true,
+            automatic_redelivery: true,
+            authorized_events: AuthorizedEvents::everything(),
+            secret: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Webhook {
+    pub id: String,
+    pub url: String,
+    pub enabled: bool,
+    pub automatic_redelivery: bool,
+    pub authorized_events: AuthorizedEvents,
+    /// Only returned when the webhook is created
+    pub secret: Option<String>,
+}
+
+string_enum! {
+    pub enum WebhookDeliveryStatus {
+        /// The server could not be reached
+        Failed => "Failed",
+        HttpError => "HttpError",
+        HttpSuccess => "HttpSuccess",
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookDelivery {
+    pub id: String,
+    #[serde(with = "timestamp::seconds")]
+    pub timestamp: DateTime,
+    pub http_code: Option<u16>,
+    pub error_message: Option<String>,
+    pub status: WebhookDeliveryStatus,
+    /// Whether the request payload was deleted and can't be redelivered
+    #[serde(default)]
+    pub pruned: bool,
+}
+
+impl WebhookDelivery {
+    pub fn is_failed(&self) -> bool {
+        self.status != WebhookDeliveryStatus::HttpSuccess
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+
+    pub const PAYLOAD: &str = r#"{"deliveryId":"Dk2vUq4jXLzRzyyQ8Dyuds","webhookId":"2YJ4iWgS2Ws3ioXxJXbdLn","originalDeliveryId":"Dk2vUq4jXLzRzyyQ8Dyuds","isRedelivery":false,"type":"InvoiceSettled","timestamp":1689615300,"storeId":"8vZCm1j3mNy2BcUVCgTbQaGzzRd1V1tXxpuNwDLfprbi","invoiceId":"PJuNNh4AZsfGbLqsLP1Ekq","manuallyMarked":false,"overPaid":false}"#;
+
+    #[test]
+    fn test_parse_webhook_payload() {
+        let parsed: WebhookPayload = serde_json::from_str(PAYLOAD).unwrap();
+        assert_eq!(parsed.event_type, WebhookEventType::InvoiceSettled);
+        assert_eq!(timestamp::to_millis(&parsed.timestamp), 1689615300000);
+        assert_eq!(parsed.extra["manuallyMarked"], false);
+    }
+}
@@ -0,0 +1,103 @@
+//! QR code rendering for payment URIs, enabled by the `qr` feature.
+
+use qrcode::render::svg;
+use qrcode::{Color, EcLevel, QrCode};
+
+use crate::core::client::Error;
+use crate::models::{Bip21Uri, Bolt11Invoice, Lnurl};
+
+/// Modules of empty space around the code, as recommended by the QR specification
+const QUIET_ZONE: u32 = 4;
+
+pub trait ToQrCode {
+    /// Content encoded in the QR code
+    fn qr_data(&self) -> String;
+
+    fn to_qr_code(&self) -> Result<QrCode, Error> {
+        Ok(QrCode::with_error_correction_level(
+            self.qr_data(),
+            EcLevel::M,
+        )?)
+    }
+
+    fn to_qr_svg(&self) -> Result<String, Error> {
+        Ok(self
+            .to_qr_code()?
+            .render::<svg::Color>()
+            .min_dimensions(256, 256)
+            .build())
+    }
+
+    /// Renders a grayscale PNG where every module is `module_size` pixels wide
+    fn to_qr_png(&self, module_size: u32) -> Result<Vec<u8>, Error> {
+        let code = self.to_qr_code()?;
+        let module_size = module_size.max(1);
+        let modules = code.width() as u32;
+        let size = (modules + 2 * QUIET_ZONE) * module_size;
+
+        let colors = code.to_colors();
+        let mut pixels = vec![0xFF; (size * size) as usize];
+        for y in 0..size {
+            for x in 0..size {
+                let (mx, my) = (x / module_size, y / module_size);
+                if mx < QUIET_ZONE || my < QUIET_ZONE {
+                    continue;
+                }
+                let (mx, my) = (mx - QUIET_ZONE, my - QUIET_ZONE);
+                if mx >= modules || my >= modules {
+                    continue;
+                }
+
+                if colors[(my * modules + mx) as usize] == Color::Dark {
+                    pixels[(y * size + x) as usize] = 0x00;
+                }
+            }
+        }
+
+        let mut buffer = Vec::new();
+        let mut encoder = png::Encoder::new(&mut buffer, size, size);
+        encoder.set_color(png::ColorType::Grayscale);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header()?.write_image_data(&pixels)?;
+
+        Ok(buffer)
+    }
+}
+
+impl ToQrCode for Bip21Uri {
+    fn qr_data(&self) -> String {
+        self.to_string()
+    }
+}
+
+// Bech32 strings are encoded uppercase so that the QR code can use the denser alphanumeric mode
+
+impl ToQrCode for Bolt11Invoice {
+    fn qr_data(&self) -> String {
+        self.to_string().to_uppercase()
+    }
+}
+
+impl ToQrCode for Lnurl {
+    fn qr_data(&self) -> String {
+        self.to_string().to_uppercase()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_render_qr() {
+        let uri: Bip21Uri = "bitcoin:tb1qxzv66jwlm5pphum53alctrsdr7stfahc6hnxeg?amount=0.0026093"
+            .parse()
+            .unwrap();
+
+        let svg = uri.to_qr_svg().unwrap();
+        assert!(svg.contains("<svg"));
+
+        let png = uri.to_qr_png(4).unwrap();
+        assert_eq!(&png[1..4], b"PNG");
+    }
+}
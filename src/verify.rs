@@ -0,0 +1,596 @@
+//! Independent verification of on-chain payments, enabled by the `verify` feature.
+//!
+//! The payments BTCPay lists for an invoice are looked up on a [`ChainSource`] (a Bitcoin Core
+//! node or an Esplora server) to make sure they pay the invoice address, with the expected amount
+//! and enough confirmations.
+
+use std::str::FromStr;
+
+use async_trait::async_trait;
+use bitcoin::{Amount, Denomination, Txid};
+use serde::Deserialize;
+
+use crate::core::client::Error;
+use crate::models::{CryptoInfo, Invoice, PaymentMethodId};
+
+/// An output as seen by the chain source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TxOutInfo {
+    pub address: Option<String>,
+    pub value: Amount,
+    /// Zero while the transaction is in the mempool
+    pub confirmations: u32,
+}
+
+#[async_trait]
+pub trait ChainSource: Send + Sync {
+    /// Looks up an output, returning `None` if the transaction is unknown
+    async fn get_output(&self, txid: &Txid, vout: u32) -> Result<Option<TxOutInfo>, Error>;
+}
+
+/// Bitcoin Core JSON-RPC. Looking up arbitrary transactions requires `-txindex`.
+#[derive(Debug, Clone)]
+pub struct BitcoinCoreRpc {
+    url: String,
+    auth: Option<(String, String)>,
+    client: reqwest::Client,
+}
+
+impl BitcoinCoreRpc {
+    pub fn new(url: &str, auth: Option<(&str, &str)>) -> Self {
+        BitcoinCoreRpc {
+            url: url.to_string(),
+            auth: auth.map(|(user, pass)| (user.to_string(), pass.to_string())),
+            client: reqwest::Client::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl ChainSource for BitcoinCoreRpc {
+    async fn get_output(&self, txid: &Txid, vout: u32) -> Result<Option<TxOutInfo>, Error> {
+        // Error returned for unknown transactions
+        const RPC_INVALID_ADDRESS_OR_KEY: i64 = -5;
+
+        #[derive(Deserialize)]
+        struct ScriptPubKey {
+            address: Option<String>,
+            #[serde(default)]
+            addresses: Vec<String>,
+        }
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct Vout {
+            value: f64,
+            script_pub_key: ScriptPubKey,
+        }
+        #[derive(Deserialize)]
+        struct Transaction {
+            vout: Vec<Vout>,
+            #[serde(default)]
+            confirmations: u32,
+        }
+
+        let mut request = self.client.post(&self.url).json(&serde_json::json!({
+            "jsonrpc": "1.0",
+            "id": "btcpay",
+            "method": "getrawtransaction",
+            "params": [txid.to_string(), true],
+        }));
+        if let Some((user, pass)) = &self.auth {
+            request = request.basic_auth(user, Some(pass));
+        }
+        let response = request.send().await?.json::<serde_json::Value>().await?;
+
+        match response["error"]["code"].as_i64() {
+            Some(RPC_INVALID_ADDRESS_OR_KEY) => return Ok(None),
+            Some(_) => return Err(Error::ChainSource(response["error"].to_string())),
+            None => {}
+        }
+
+        let transaction: Transaction = serde_json::from_value(response["result"].clone())?;
+        let output = match transaction.vout.into_iter().nth(vout as usize) {
+            Some(output) => output,
+            None => return Ok(None),
+        };
+
+        // Nodes before v22 only report the deprecated `addresses` list
+        let ScriptPubKey { address, addresses } = output.script_pub_key;
+
+        Ok(Some(TxOutInfo {
+            address: address.or_else(|| addresses.into_iter().next()),
+            value: Amount::from_btc(output.value).map_err(|_| Error::InvalidResponse)?,
+            confirmations: transaction.confirmations,
+        }))
+    }
+}
+
+/// Esplora REST API, like the one served by Blockstream or mempool.space
+#[derive(Debug, Clone)]
+pub struct Esplora {
+    base_url: String,
+    client: reqwest::Client,
+}
+
+impl Esplora {
+    pub fn new(base_url: &str) -> Self {
+        Esplora {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            client: reqwest::Client::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl ChainSource for Esplora {
+    async fn get_output(&self, txid: &Txid, vout: u32) -> Result<Option<TxOutInfo>, Error> {
+        #[derive(Deserialize)]
+        struct Vout {
+            value: u64,
+            scriptpubkey_address: Option<String>,
+        }
+        #[derive(Deserialize)]
+        struct Status {
+            confirmed: bool,
+            block_height: Option<u32>,
+        }
+        #[derive(Deserialize)]
+        struct Transaction {
+            vout: Vec<Vout>,
+            status: Status,
+        }
+
+        let response = self
+            .client
+            .get(format!("{}/tx/{}", self.base_url, txid))
+            .send()
+            .await?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let transaction: Transaction = response.error_for_status()?.json().await?;
+
+        let output = match transaction.vout.into_iter().nth(vout as usize) {
+            Some(output) => output,
+            None => return Ok(None),
+        };
+
+        let confirmations = match (
+            transaction.status.confirmed,
+            transaction.status.block_height,
+        ) {
+            (true, Some(height)) => {
+                let tip: u32 = self
+                    .client
+                    .get(format!("{}/blocks/tip/height", self.base_url))
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .text()
+                    .await?
+                    .trim()
+                    .parse()
+                    .map_err(|_| Error::InvalidResponse)?;
+
+                tip.saturating_sub(height) + 1
+            }
+            _ => 0,
+        };
+
+        Ok(Some(TxOutInfo {
+            address: output.scriptpubkey_address,
+            value: Amount::from_sat(output.value),
+            confirmations,
+        }))
+    }
+}
+
+/// A payment as listed by BTCPay in `CryptoInfo::payments`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InvoicePayment {
+    /// `<txid>-<vout>` for on-chain payments
+    pub id: String,
+    pub value: f64,
+    pub destination: Option<String>,
+    #[serde(default)]
+    pub confirmed: bool,
+    #[serde(default)]
+    pub completed: bool,
+}
+
+impl InvoicePayment {
+    pub fn outpoint(&self) -> Option<(Txid, u32)> {
+        let (txid, vout) = self.id.rsplit_once('-')?;
+
+        Some((Txid::from_str(txid).ok()?, vout.parse().ok()?))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Mismatch {
+    /// The payment id is not a valid outpoint
+    InvalidPaymentId(String),
+    /// The chain source doesn't know the transaction or output
+    MissingOutput(Txid, u32),
+    WrongAddress {
+        txid: Txid,
+        vout: u32,
+        expected: String,
+        found: Option<String>,
+    },
+    WrongAmount {
+        txid: Txid,
+        vout: u32,
+        expected: Amount,
+        found: Amount,
+    },
+    /// BTCPay considers the payment confirmed, but the chain source doesn't
+    Unconfirmed(Txid, u32),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerificationReport {
+    pub address: String,
+    /// Amount that has to be paid, including the network fee
+    pub expected: Amount,
+    /// Amount received with at least the required confirmations
+    pub confirmed: Amount,
+    /// Amount received with fewer confirmations
+    pub pending: Amount,
+    pub mismatches: Vec<Mismatch>,
+}
+
+impl VerificationReport {
+    /// Whether the chain source agrees with BTCPay and the confirmed amount covers the invoice
+    pub fn is_paid(&self) -> bool {
+        self.mismatches.is_empty() && self.confirmed >= self.expected
+    }
+}
+
+/// Checks the BTC on-chain payments of `invoice` against `source`.
+///
+/// Returns `None` if the invoice can't be paid on-chain in BTC.
+pub async fn verify_invoice<S: ChainSource + ?Sized>(
+    invoice: &Invoice,
+    source: &S,
+    min_confirmations: u32,
+) -> Result<Option<VerificationReport>, Error> {
+    let info = match invoice
+        .crypto_info
+        .iter()
+        .find(|info| info.payment_method_id() == PaymentMethodId::on_chain("BTC"))
+    {
+        Some(info) => info,
+        None => return Ok(None),
+    };
+
+    verify_crypto_info(info, source, min_confirmations).await
+}
+
+async fn verify_crypto_info<S: ChainSource + ?Sized>(
+    info: &CryptoInfo,
+    source: &S,
+    min_confirmations: u32,
+) -> Result<Option<VerificationReport>, Error> {
+    let address = match &info.address {
+        Some(address) => address.clone(),
+        None => return Ok(None),
+    };
+    let expected = Amount::from_str_in(&info.total_due, Denomination::Bitcoin)
+        .map_err(|_| Error::InvalidResponse)?;
+
+    let mut report = VerificationReport {
+        address,
+        expected,
+        confirmed: Amount::ZERO,
+        pending: Amount::ZERO,
+        mismatches: Vec::new(),
+    };
+
+    for payment in &info.payments {
+        let payment: InvoicePayment = serde_json::from_value(payment.clone())?;
+        let (txid, vout) = match payment.outpoint() {
+            Some(outpoint) => outpoint,
+            None => {
+                report
+                    .mismatches
+                    .push(Mismatch::InvalidPaymentId(payment.id.clone()));
+                continue;
+            }
+        };
+
+        let output = match source.get_output(&txid, vout).await? {
+            Some(output) => output,
+            None => {
+                report.mismatches.push(Mismatch::MissingOutput(txid, vout));
+                continue;
+            }
+        };
+
+        if output.address.as_ref() != Some(&report.address) {
+            report.mismatches.push(Mismatch::WrongAddress {
+                txid,
+                vout,
+                expected: report.address.clone(),
+                found: output.address.clone(),
+            });
+            continue;
+        }
+        let value = Amount::from_btc(payment.value).map_err(|_| Error::InvalidResponse)?;
+        if output.value != value {
+            report.mismatches.push(Mismatch::WrongAmount {
+                txid,
+                vout,
+                expected: value,
+                found: output.value,
+            });
+        }
+
+        if output.confirmations >= min_confirmations {
+            report.confirmed += output.value;
+        } else {
+            if payment.confirmed && output.confirmations == 0 {
+                report.mismatches.push(Mismatch::Unconfirmed(txid, vout));
+            }
+            report.pending += output.value;
+        }
+    }
+
+    Ok(Some(report))
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use mockito::{Matcher, Server};
+
+    use super::*;
+    use crate::models::invoice::test::get_test_invoices_json;
+
+    const ADDRESS: &str = "tb1qxzv66jwlm5pphum53alctrsdr7stfahc6hnxeg";
+    const TXID: &str = "4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b";
+
+    struct MockChain(HashMap<(Txid, u32), TxOutInfo>);
+
+    #[async_trait]
+    impl ChainSource for MockChain {
+        async fn get_output(&self, txid: &Txid, vout: u32) -> Result<Option<TxOutInfo>, Error> {
+            Ok(self.0.get(&(*txid, vout)).cloned())
+        }
+    }
+
+    fn get_test_invoice(payments: serde_json::Value) -> Invoice {
+        let mut json: serde_json::Value =
+            serde_json::from_str(get_test_invoices_json()[1]).unwrap();
+        json["cryptoInfo"][0]["payments"] = payments;
+
+        serde_json::from_value(json).unwrap()
+    }
+
+    fn get_test_chain(address: &str, sats: u64, confirmations: u32) -> MockChain {
+        let mut outputs = HashMap::new();
+        outputs.insert(
+            (TXID.parse().unwrap(), 1),
+            TxOutInfo {
+                address: Some(address.to_string()),
+                value: Amount::from_sat(sats),
+                confirmations,
+            },
+        );
+
+        MockChain(outputs)
+    }
+
+    #[tokio::test]
+    async fn test_verify_paid_invoice() {
+        let invoice = get_test_invoice(serde_json::json!([{
+            "id": format!("{}-1", TXID),
+            "value": 0.0026093,
+            "destination": ADDRESS,
+            "confirmed": true,
+            "completed": true,
+        }]));
+
+        let report = verify_invoice(&invoice, &get_test_chain(ADDRESS, 260930, 3), 1)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(report.is_paid());
+        assert_eq!(report.confirmed, Amount::from_sat(260930));
+
+        let report = verify_invoice(&invoice, &get_test_chain(ADDRESS, 260930, 0), 1)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(!report.is_paid());
+        assert_eq!(report.pending, Amount::from_sat(260930));
+        assert_eq!(
+            report.mismatches,
+            vec![Mismatch::Unconfirmed(TXID.parse().unwrap(), 1)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_verify_mismatches() {
+        let invoice = get_test_invoice(serde_json::json!([
+            {"id": format!("{}-1", TXID), "value": 0.0026093, "destination": ADDRESS},
+            {"id": format!("{}-7", TXID), "value": 0.001, "destination": ADDRESS},
+        ]));
+
+        let report = verify_invoice(&invoice, &get_test_chain(ADDRESS, 1000, 6), 1)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(!report.is_paid());
+        assert_eq!(
+            report.mismatches,
+            vec![
+                Mismatch::WrongAmount {
+                    txid: TXID.parse().unwrap(),
+                    vout: 1,
+                    expected: Amount::from_sat(260930),
+                    found: Amount::from_sat(1000),
+                },
+                Mismatch::MissingOutput(TXID.parse().unwrap(), 7),
+            ]
+        );
+
+        let other = "tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx";
+        let report = verify_invoice(&invoice, &get_test_chain(other, 260930, 6), 1)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(matches!(
+            report.mismatches[0],
+            Mismatch::WrongAddress { .. }
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_bitcoin_core_rpc() {
+        let mut server = Server::new_async().await;
+        let rpc = BitcoinCoreRpc::new(&server.url(), Some(("user", "pass")));
+        let txid: Txid = TXID.parse().unwrap();
+
+        let mock = server
+            .mock("POST", "/")
+            .match_header("authorization", "Basic dXNlcjpwYXNz")
+            .match_body(Matcher::PartialJsonString(format!(
+                r#"{{"method":"getrawtransaction","params":["{}",true]}}"#,
+                TXID
+            )))
+            .with_body(format!(
+                r#"{{"result":{{"confirmations":3,"vout":[
+                    {{"value":0.1,"scriptPubKey":{{"address":"{other}"}}}},
+                    {{"value":0.0026093,"scriptPubKey":{{"address":"{address}"}}}}
+                ]}},"error":null,"id":"btcpay"}}"#,
+                other = "tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx",
+                address = ADDRESS
+            ))
+            .create_async()
+            .await;
+        assert_eq!(
+            rpc.get_output(&txid, 1).await.unwrap(),
+            Some(TxOutInfo {
+                address: Some(ADDRESS.to_string()),
+                value: Amount::from_sat(260930),
+                confirmations: 3,
+            })
+        );
+        assert_eq!(rpc.get_output(&txid, 2).await.unwrap(), None);
+        mock.remove_async().await;
+
+        // Nodes before v22 only report `addresses`, and mempool transactions no confirmations
+        let mock = server
+            .mock("POST", "/")
+            .with_body(format!(
+                r#"{{"result":{{"vout":[{{"value":0.00000001,"scriptPubKey":{{"addresses":["{}"]}}}}]}},"error":null}}"#,
+                ADDRESS
+            ))
+            .create_async()
+            .await;
+        assert_eq!(
+            rpc.get_output(&txid, 0).await.unwrap(),
+            Some(TxOutInfo {
+                address: Some(ADDRESS.to_string()),
+                value: Amount::from_sat(1),
+                confirmations: 0,
+            })
+        );
+        mock.remove_async().await;
+
+        let mock = server
+            .mock("POST", "/")
+            .with_status(500)
+            .with_body(r#"{"result":null,"error":{"code":-5,"message":"No such mempool or blockchain transaction"}}"#)
+            .create_async()
+            .await;
+        assert_eq!(rpc.get_output(&txid, 0).await.unwrap(), None);
+        mock.remove_async().await;
+
+        server
+            .mock("POST", "/")
+            .with_status(500)
+            .with_body(r#"{"result":null,"error":{"code":-8,"message":"parameter 1 must be hexadecimal"}}"#)
+            .create_async()
+            .await;
+        assert!(matches!(
+            rpc.get_output(&txid, 0).await,
+            Err(Error::ChainSource(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_esplora() {
+        let mut server = Server::new_async().await;
+        let esplora = Esplora::new(&format!("{}/api/", server.url()));
+        let txid: Txid = TXID.parse().unwrap();
+
+        let tx = server
+            .mock("GET", format!("/api/tx/{}", TXID).as_str())
+            .with_body(format!(
+                r#"{{"vout":[{{"value":1000,"scriptpubkey_address":null}},{{"value":260930,"scriptpubkey_address":"{}"}}],
+                    "status":{{"confirmed":true,"block_height":2500000}}}}"#,
+                ADDRESS
+            ))
+            .expect(2)
+            .create_async()
+            .await;
+        let tip = server
+            .mock("GET", "/api/blocks/tip/height")
+            .with_body("2500002")
+            .expect(1)
+            .create_async()
+            .await;
+        assert_eq!(
+            esplora.get_output(&txid, 1).await.unwrap(),
+            Some(TxOutInfo {
+                address: Some(ADDRESS.to_string()),
+                value: Amount::from_sat(260930),
+                confirmations: 3,
+            })
+        );
+        assert_eq!(esplora.get_output(&txid, 2).await.unwrap(), None);
+        tx.assert_async().await;
+        tip.assert_async().await;
+        tx.remove_async().await;
+
+        // The tip isn't needed for mempool transactions
+        let tx = server
+            .mock("GET", format!("/api/tx/{}", TXID).as_str())
+            .with_body(r#"{"vout":[{"value":1000,"scriptpubkey_address":null}],"status":{"confirmed":false}}"#)
+            .create_async()
+            .await;
+        assert_eq!(
+            esplora.get_output(&txid, 0).await.unwrap(),
+            Some(TxOutInfo {
+                address: None,
+                value: Amount::from_sat(1000),
+                confirmations: 0,
+            })
+        );
+        tip.assert_async().await;
+        tx.remove_async().await;
+
+        let tx = server
+            .mock("GET", format!("/api/tx/{}", TXID).as_str())
+            .with_status(404)
+            .with_body("Transaction not found")
+            .create_async()
+            .await;
+        assert_eq!(esplora.get_output(&txid, 0).await.unwrap(), None);
+        tx.remove_async().await;
+
+        server
+            .mock("GET", format!("/api/tx/{}", TXID).as_str())
+            .with_status(500)
+            .create_async()
+            .await;
+        assert!(matches!(
+            esplora.get_output(&txid, 0).await,
+            Err(Error::Request(_))
+        ));
+    }
+}
@@ -0,0 +1,125 @@
+//! actix-web handlers, enabled by the `actix` feature.
+//!
+//! ```ignore
+//! let handler = Arc::new(WebhookHandler::new(on_event).secret(&secret));
+//! App::new().route("/btcpay/webhook", webhook_route(handler))
+//! ```
+
+use std::fmt::Display;
+use std::future::Future;
+use std::sync::Arc;
+
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpRequest, HttpResponse, Route};
+
+use super::{status_code, WebhookHandler, SIGNATURE_HEADER};
+use crate::models::WebhookEvent;
+
+fn reply(code: u16) -> HttpResponse {
+    HttpResponse::new(StatusCode::from_u16(code).expect("valid status code"))
+}
+
+/// Route accepting Greenfield webhook deliveries
+pub fn webhook_route<F, Fut, E>(handler: Arc<WebhookHandler<F>>) -> Route
+where
+    F: Fn(WebhookEvent) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<(), E>> + Send + 'static,
+    E: Display + 'static,
+{
+    web::post().to(move |request: HttpRequest, body: web::Bytes| {
+        let handler = Arc::clone(&handler);
+        async move {
+            let signature = request
+                .headers()
+                .get(SIGNATURE_HEADER)
+                .and_then(|value| value.to_str().ok());
+
+            reply(status_code(&handler.handle_webhook(signature, &body).await))
+        }
+    })
+}
+
+/// Route accepting legacy IPNs
+pub fn ipn_route<F, Fut, E>(handler: Arc<WebhookHandler<F>>) -> Route
+where
+    F: Fn(WebhookEvent) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<(), E>> + Send + 'static,
+    E: Display + 'static,
+{
+    web::post().to(move |body: web::Bytes| {
+        let handler = Arc::clone(&handler);
+        async move { reply(status_code(&handler.handle_ipn(&body).await)) }
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Mutex;
+
+    use actix_web::{test, App};
+    use mockito::Server;
+
+    use super::*;
+    use crate::models::webhook::test::PAYLOAD;
+    use crate::webhook::test::{
+        ipn_client, ipn_recorder, mock_ipn_invoice, sign, IPN_INVOICE_ID, SECRET,
+    };
+
+    #[test]
+    fn test_webhook_route() {
+        actix_rt::System::new().block_on(async {
+            let handler = Arc::new(
+                WebhookHandler::new(|_| async { Ok::<_, std::convert::Infallible>(()) })
+                    .secret(SECRET),
+            );
+            let app =
+                test::init_service(App::new().route("/webhook", webhook_route(handler))).await;
+
+            let request = test::TestRequest::post()
+                .uri("/webhook")
+                .set_payload(PAYLOAD)
+                .to_request();
+            let response = test::call_service(&app, request).await;
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+            let request = test::TestRequest::post()
+                .uri("/webhook")
+                .insert_header((SIGNATURE_HEADER, sign(PAYLOAD.as_bytes())))
+                .set_payload(PAYLOAD)
+                .to_request();
+            let response = test::call_service(&app, request).await;
+            assert_eq!(response.status(), StatusCode::OK);
+        });
+    }
+
+    #[test]
+    fn test_ipn_route() {
+        actix_rt::System::new().block_on(async {
+            let mut server = Server::new_async().await;
+            let mock = mock_ipn_invoice(&mut server, 1).await;
+            let received = Arc::new(Mutex::new(Vec::new()));
+            let handler = Arc::new(
+                WebhookHandler::new(ipn_recorder(Arc::clone(&received)))
+                    .client(ipn_client(&server.url())),
+            );
+            let app = test::init_service(App::new().route("/ipn", ipn_route(handler))).await;
+
+            let request = test::TestRequest::post()
+                .uri("/ipn")
+                .set_payload(format!(r#"{{"id":"{}"}}"#, IPN_INVOICE_ID))
+                .to_request();
+            let response = test::call_service(&app, request).await;
+            assert_eq!(response.status(), StatusCode::OK);
+
+            let request = test::TestRequest::post()
+                .uri("/ipn")
+                .set_payload("{}")
+                .to_request();
+            let response = test::call_service(&app, request).await;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+            assert_eq!(*received.lock().unwrap(), vec![IPN_INVOICE_ID.to_string()]);
+            mock.assert_async().await;
+        });
+    }
+}
@@ -0,0 +1,116 @@
+//! axum handlers, enabled by the `axum` feature.
+//!
+//! ```ignore
+//! let handler = Arc::new(WebhookHandler::new(on_event).secret(&secret));
+//! let app = Router::new().route("/btcpay/webhook", webhook_route(handler));
+//! ```
+
+use std::fmt::Display;
+use std::future::Future;
+use std::sync::Arc;
+
+use axum::body::Bytes;
+use axum::http::{HeaderMap, StatusCode};
+use axum::routing::{post, MethodRouter};
+
+use super::{status_code, WebhookHandler, SIGNATURE_HEADER};
+use crate::models::WebhookEvent;
+
+fn reply(code: u16) -> StatusCode {
+    StatusCode::from_u16(code).expect("valid status code")
+}
+
+/// Route accepting Greenfield webhook deliveries
+pub fn webhook_route<S, F, Fut, E>(handler: Arc<WebhookHandler<F>>) -> MethodRouter<S>
+where
+    S: Clone + Send + Sync + 'static,
+    F: Fn(WebhookEvent) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<(), E>> + Send + 'static,
+    E: Display + 'static,
+{
+    post(move |headers: HeaderMap, body: Bytes| async move {
+        let signature = headers
+            .get(SIGNATURE_HEADER)
+            .and_then(|value| value.to_str().ok());
+
+        reply(status_code(&handler.handle_webhook(signature, &body).await))
+    })
+}
+
+/// Route accepting legacy IPNs
+pub fn ipn_route<S, F, Fut, E>(handler: Arc<WebhookHandler<F>>) -> MethodRouter<S>
+where
+    S: Clone + Send + Sync + 'static,
+    F: Fn(WebhookEvent) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<(), E>> + Send + 'static,
+    E: Display + 'static,
+{
+    post(move |body: Bytes| async move { reply(status_code(&handler.handle_ipn(&body).await)) })
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Mutex;
+
+    use axum::body::Body;
+    use axum::http::Request;
+    use axum::Router;
+    use mockito::Server;
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::models::webhook::test::PAYLOAD;
+    use crate::webhook::test::{
+        ipn_client, ipn_recorder, mock_ipn_invoice, sign, IPN_INVOICE_ID, SECRET,
+    };
+
+    #[tokio::test]
+    async fn test_webhook_route() {
+        let handler = Arc::new(
+            WebhookHandler::new(|_| async { Ok::<_, std::convert::Infallible>(()) }).secret(SECRET),
+        );
+        let app: Router = Router::new().route("/webhook", webhook_route(handler));
+
+        let request = |signature: &str| {
+            Request::post("/webhook")
+                .header(SIGNATURE_HEADER, signature)
+                .body(Body::from(PAYLOAD))
+                .unwrap()
+        };
+
+        let response = app.clone().oneshot(request("sha256=00")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let signature = sign(PAYLOAD.as_bytes());
+        let response = app.clone().oneshot(request(&signature)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = app.oneshot(request(&signature)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_ipn_route() {
+        let mut server = Server::new_async().await;
+        let mock = mock_ipn_invoice(&mut server, 1).await;
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let handler = Arc::new(
+            WebhookHandler::new(ipn_recorder(Arc::clone(&received)))
+                .client(ipn_client(&server.url())),
+        );
+        let app: Router = Router::new().route("/ipn", ipn_route(handler));
+
+        let request = |body: String| Request::post("/ipn").body(Body::from(body)).unwrap();
+
+        let response = app
+            .clone()
+            .oneshot(request(format!(r#"{{"id":"{}"}}"#, IPN_INVOICE_ID)))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = app.oneshot(request("{}".into())).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        assert_eq!(*received.lock().unwrap(), vec![IPN_INVOICE_ID.to_string()]);
+        mock.assert_async().await;
+    }
+}
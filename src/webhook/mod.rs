@@ -0,0 +1,455 @@
+//! Receiving webhooks and IPNs from the server.
+//!
+//! [`WebhookHandler`] verifies and deduplicates notifications independently of the HTTP
+//! framework. Ready-made handlers are available for axum and actix-web, behind the `axum` and
+//! `actix` features.
+
+use std::collections::{HashSet, VecDeque};
+use std::fmt::Display;
+use std::future::Future;
+use std::sync::Mutex;
+
+use bitcoin::hashes::hex::FromHex;
+use bitcoin::hashes::hmac::{Hmac, HmacEngine};
+use bitcoin::hashes::{cmp, sha256, Hash, HashEngine};
+
+use crate::core::client::{BTCPayClient, Error};
+use crate::models::{WebhookEvent, WebhookPayload};
+
+#[cfg(feature = "actix")]
+pub mod actix;
+#[cfg(feature = "axum")]
+pub mod axum;
+
+/// Header carrying the signature of webhook deliveries
+pub const SIGNATURE_HEADER: &str = "BTCPay-Sig";
+
+/// Number of delivery ids remembered by default to detect duplicates
+const DEFAULT_DEDUPE_CAPACITY: usize = 1024;
+
+/// Checks a `sha256=<hex>` signature header against the HMAC of `body`
+pub fn verify_signature(secret: &str, body: &[u8], signature: &str) -> bool {
+    let signature = match signature
+        .strip_prefix("sha256=")
+        .and_then(|hex| Vec::<u8>::from_hex(hex).ok())
+    {
+        Some(signature) => signature,
+        None => return false,
+    };
+
+    let mut engine = HmacEngine::<sha256::Hash>::new(secret.as_bytes());
+    engine.input(body);
+    let expected = Hmac::<sha256::Hash>::from_engine(engine);
+
+    signature.len() == expected.as_byte_array().len()
+        && cmp::fixed_time_eq(&signature, expected.as_byte_array())
+}
+
+/// Outcome of a notification accepted by [`WebhookHandler`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Delivery {
+    Dispatched,
+    /// Already processed, the callback was not called again
+    Duplicate,
+}
+
+/// HTTP status to reply with, so that the server only redelivers notifications that can succeed
+/// later
+pub fn status_code(result: &Result<Delivery, Error>) -> u16 {
+    match result {
+        Ok(_) => 200,
+        Err(Error::InvalidWebhookSignature) => 401,
+        Err(Error::JSON(_) | Error::InvalidResponse | Error::NetworkMismatch(..)) => 400,
+        Err(Error::Request(_)) => 502,
+        Err(Error::WebhookInProgress(_)) => 503,
+        Err(_) => 500,
+    }
+}
+
+#[derive(Debug)]
+struct SeenDeliveries {
+    order: VecDeque<String>,
+    ids: HashSet<String>,
+    capacity: usize,
+    /// Ids whose callback hasn't returned yet
+    in_flight: HashSet<String>,
+}
+
+impl SeenDeliveries {
+    /// Returns `false` if `id` was already seen
+    fn insert(&mut self, id: &str) -> bool {
+        if !self.ids.insert(id.to_string()) {
+            return false;
+        }
+
+        self.order.push_back(id.to_string());
+        while self.order.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.ids.remove(&oldest);
+            }
+        }
+
+        true
+    }
+}
+
+/// Clears an in-flight id once its callback has returned, even if it panicked
+struct InFlight<'a> {
+    seen: &'a Mutex<SeenDeliveries>,
+    id: &'a str,
+}
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        if let Ok(mut seen) = self.seen.lock() {
+            seen.in_flight.remove(self.id);
+        }
+    }
+}
+
+/// Verifies notifications, drops duplicates and passes the rest to a callback.
+///
+/// Webhooks are rejected until a [`secret`](WebhookHandler::secret) is set, and IPNs until a
+/// [`client`](WebhookHandler::client) is set to fetch the invoice back from the server. A
+/// notification only counts as processed once the callback succeeds: while it runs, the same
+/// notification is answered with a retryable error, and after a failure it's processed again.
+#[derive(Debug)]
+pub struct WebhookHandler<F> {
+    callback: F,
+    secret: Option<String>,
+    client: Option<BTCPayClient>,
+    collapse_redeliveries: bool,
+    seen: Mutex<SeenDeliveries>,
+}
+
+impl<F, Fut, E> WebhookHandler<F>
+where
+    F: Fn(WebhookEvent) -> Fut + Send + Sync,
+    Fut: Future<Output = Result<(), E>> + Send,
+    E: Display,
+{
+    pub fn new(callback: F) -> Self {
+        WebhookHandler {
+            callback,
+            secret: None,
+            client: None,
+            collapse_redeliveries: false,
+            seen: Mutex::new(SeenDeliveries {
+                order: VecDeque::new(),
+                ids: HashSet::new(),
+                capacity: DEFAULT_DEDUPE_CAPACITY,
+                in_flight: HashSet::new(),
+            }),
+        }
+    }
+
+    /// Secret of the webhook, used to verify signatures
+    pub fn secret(mut self, secret: &str) -> Self {
+        self.secret = Some(secret.to_string());
+        self
+    }
+
+    /// Client used to fetch back the invoices of IPNs
+    pub fn client(mut self, client: BTCPayClient) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    /// Treats redeliveries of an event that was already processed as duplicates. Off by default,
+    /// since redeliveries are usually requested by the merchant to have the event processed again.
+    pub fn collapse_redeliveries(mut self, collapse: bool) -> Self {
+        self.collapse_redeliveries = collapse;
+        self
+    }
+
+    /// Number of delivery ids remembered to detect duplicates
+    pub fn dedupe_capacity(self, capacity: usize) -> Self {
+        self.seen.lock().unwrap().capacity = capacity;
+        self
+    }
+
+    /// Handles the body of a webhook delivery and the value of its [`SIGNATURE_HEADER`]
+    pub async fn handle_webhook(
+        &self,
+        signature: Option<&str>,
+        body: &[u8],
+    ) -> Result<Delivery, Error> {
+        let verified = match (&self.secret, signature) {
+            (Some(secret), Some(signature)) => verify_signature(secret, body, signature),
+            _ => false,
+        };
+        if !verified {
+            return Err(Error::InvalidWebhookSignature);
+        }
+
+        let payload: WebhookPayload = serde_json::from_slice(body)?;
+        let id = match &payload.original_delivery_id {
+            Some(original) if self.collapse_redeliveries => format!("webhook:{}", original),
+            _ => format!("webhook:{}", payload.delivery_id),
+        };
+
+        self.dispatch(&id, WebhookEvent::Webhook(Box::new(payload)))
+            .await
+    }
+
+    /// Handles the body of an IPN. Its content is not trusted: only the invoice id is used, to
+    /// fetch the invoice from the server.
+    pub async fn handle_ipn(&self, body: &[u8]) -> Result<Delivery, Error> {
+        let client = self.client.as_ref().ok_or_else(|| {
+            Error::WebhookNotConfigured("a client is required to verify IPNs".into())
+        })?;
+
+        let body: serde_json::Value = serde_json::from_slice(body)?;
+        // Extended notifications wrap the invoice in `data`
+        let invoice_id = body["data"]["id"]
+            .as_str()
+            .or_else(|| body["id"].as_str())
+            .ok_or(Error::InvalidResponse)?;
+
+        let invoice = client.get_invoice(invoice_id).await?;
+        let id = format!(
+            "ipn:{}:{}:{:?}",
+            invoice.id, invoice.status, invoice.exception_status
+        );
+
+        self.dispatch(&id, WebhookEvent::Ipn(Box::new(invoice)))
+            .await
+    }
+
+    async fn dispatch(&self, id: &str, event: WebhookEvent) -> Result<Delivery, Error> {
+        {
+            let mut seen = self.seen.lock().unwrap();
+            if seen.ids.contains(id) {
+                return Ok(Delivery::Duplicate);
+            }
+            if !seen.in_flight.insert(id.to_string()) {
+                return Err(Error::WebhookInProgress(id.to_string()));
+            }
+        }
+        let _in_flight = InFlight {
+            seen: &self.seen,
+            id,
+        };
+
+        (self.callback)(event)
+            .await
+            .map_err(|e| Error::WebhookCallback(e.to_string()))?;
+        self.seen.lock().unwrap().insert(id);
+
+        Ok(Delivery::Dispatched)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use mockito::{Matcher, Mock, Server};
+
+    use super::*;
+    use crate::core::cryptography::Cryptography;
+    use crate::core::retry::RetryPolicy;
+    use crate::models::invoice::test::get_test_invoices_json;
+    use crate::models::webhook::test::PAYLOAD;
+
+    pub const SECRET: &str = "3SJTDjvb2A3tWFyPrTqXjD";
+
+    /// Id of the legacy invoice served by [`mock_ipn_invoice`]
+    pub const IPN_INVOICE_ID: &str = "3Wy4cKQEvmsBru5yccGbNn";
+
+    /// Serves the invoice of an IPN, expecting it to be fetched `hits` times
+    pub async fn mock_ipn_invoice(server: &mut Server, hits: usize) -> Mock {
+        server
+            .mock("GET", format!("/invoices/{}", IPN_INVOICE_ID).as_str())
+            .match_query(Matcher::Any)
+            .with_body(format!(r#"{{"data":{}}}"#, get_test_invoices_json()[1]))
+            .expect(hits)
+            .create_async()
+            .await
+    }
+
+    pub fn ipn_client(url: &str) -> BTCPayClient {
+        BTCPayClient::builder(url, Cryptography::generate_keypair())
+            .merchant("merchant")
+            .retry_policy(RetryPolicy::none())
+            .build()
+            .unwrap()
+    }
+
+    /// Callback recording in `received` the ids of the invoices received through IPNs
+    pub fn ipn_recorder(
+        received: Arc<Mutex<Vec<String>>>,
+    ) -> impl Fn(WebhookEvent) -> futures_util::future::Ready<Result<(), String>> + Send + Sync
+    {
+        move |event| {
+            futures_util::future::ready(match event {
+                WebhookEvent::Ipn(invoice) => {
+                    received.lock().unwrap().push(invoice.id);
+                    Ok(())
+                }
+                other => Err(format!("unexpected event {:?}", other)),
+            })
+        }
+    }
+
+    pub fn sign(body: &[u8]) -> String {
+        let mut engine = HmacEngine::<sha256::Hash>::new(SECRET.as_bytes());
+        engine.input(body);
+
+        format!("sha256={}", Hmac::<sha256::Hash>::from_engine(engine))
+    }
+
+    #[test]
+    fn test_verify_signature() {
+        let signature = sign(PAYLOAD.as_bytes());
+
+        assert!(verify_signature(SECRET, PAYLOAD.as_bytes(), &signature));
+        assert!(!verify_signature("other", PAYLOAD.as_bytes(), &signature));
+        assert!(!verify_signature(SECRET, b"{}", &signature));
+        assert!(!verify_signature(SECRET, PAYLOAD.as_bytes(), "sha256=00"));
+    }
+
+    #[tokio::test]
+    async fn test_handle_webhook() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&calls);
+        let handler = WebhookHandler::new(move |event| {
+            let counter = Arc::clone(&counter);
+            async move {
+                assert!(matches!(event, WebhookEvent::Webhook(_)));
+                match counter.fetch_add(1, Ordering::SeqCst) {
+                    0 => Err("database unavailable"),
+                    _ => Ok(()),
+                }
+            }
+        })
+        .secret(SECRET);
+
+        let body = PAYLOAD.as_bytes();
+        let signature = sign(body);
+
+        let result = handler.handle_webhook(None, body).await;
+        assert_eq!(status_code(&result), 401);
+        let result = handler.handle_webhook(Some(&signature), body).await;
+        assert_eq!(status_code(&result), 500);
+        let result = handler.handle_webhook(Some(&signature), body).await;
+        assert_eq!(result.unwrap(), Delivery::Dispatched);
+        let result = handler.handle_webhook(Some(&signature), body).await;
+        assert_eq!(result.unwrap(), Delivery::Duplicate);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        let result = handler.handle_webhook(Some(&sign(b"{}")), b"{}").await;
+        assert_eq!(status_code(&result), 400);
+
+        let result = handler.handle_ipn(b"{}").await;
+        assert_eq!(status_code(&result), 500);
+    }
+
+    #[tokio::test]
+    async fn test_handle_ipn() {
+        let mut server = Server::new_async().await;
+        let mock = mock_ipn_invoice(&mut server, 3).await;
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let handler = WebhookHandler::new(ipn_recorder(Arc::clone(&received)))
+            .client(ipn_client(&server.url()));
+
+        // Only the id is read from the body, the invoice is fetched from the server
+        let body = format!(
+            r#"{{"id":"{}","status":"complete","price":1}}"#,
+            IPN_INVOICE_ID
+        );
+        let result = handler.handle_ipn(body.as_bytes()).await;
+        assert_eq!(result.unwrap(), Delivery::Dispatched);
+        // Extended notifications wrap the invoice in `data`
+        let body = format!(
+            r#"{{"event":{{"code":1005}},"data":{{"id":"{}"}}}}"#,
+            IPN_INVOICE_ID
+        );
+        let result = handler.handle_ipn(body.as_bytes()).await;
+        assert_eq!(result.unwrap(), Delivery::Duplicate);
+
+        let result = handler.handle_ipn(b"{\"status\":\"paid\"}").await;
+        assert_eq!(status_code(&result), 400);
+        let result = handler
+            .handle_ipn(format!(r#"{{"id":"{}"}}"#, IPN_INVOICE_ID).as_bytes())
+            .await;
+        assert_eq!(result.unwrap(), Delivery::Duplicate);
+
+        assert_eq!(*received.lock().unwrap(), vec![IPN_INVOICE_ID.to_string()]);
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_redelivery() {
+        let mut redelivery: serde_json::Value = serde_json::from_str(PAYLOAD).unwrap();
+        redelivery["deliveryId"] = "5bMpeCmA9ph7ZRGAvEhWDD".into();
+        redelivery["isRedelivery"] = true.into();
+        let redelivery = redelivery.to_string();
+
+        for (collapse, expected) in [(false, Delivery::Dispatched), (true, Delivery::Duplicate)] {
+            let handler = WebhookHandler::new(|_| async { Ok::<_, String>(()) })
+                .secret(SECRET)
+                .collapse_redeliveries(collapse);
+
+            let body = PAYLOAD.as_bytes();
+            let result = handler.handle_webhook(Some(&sign(body)), body).await;
+            assert_eq!(result.unwrap(), Delivery::Dispatched);
+
+            let body = redelivery.as_bytes();
+            let result = handler.handle_webhook(Some(&sign(body)), body).await;
+            assert_eq!(result.unwrap(), expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_delivery() {
+        let (started_tx, started_rx) = tokio::sync::oneshot::channel();
+        let (finish_tx, finish_rx) = tokio::sync::oneshot::channel::<Result<(), String>>();
+        let channels = Mutex::new(Some((started_tx, finish_rx)));
+        let handler = WebhookHandler::new(move |_| {
+            let channels = channels.lock().unwrap().take();
+            async move {
+                match channels {
+                    Some((started, finish)) => {
+                        started.send(()).unwrap();
+                        finish.await.unwrap()
+                    }
+                    None => Ok(()),
+                }
+            }
+        })
+        .secret(SECRET);
+
+        let body = PAYLOAD.as_bytes();
+        let signature = sign(body);
+        let (first, second) = tokio::join!(handler.handle_webhook(Some(&signature), body), async {
+            started_rx.await.unwrap();
+            let result = handler.handle_webhook(Some(&signature), body).await;
+            finish_tx.send(Err("database unavailable".into())).unwrap();
+            result
+        });
+
+        assert_eq!(status_code(&second), 503);
+        assert_eq!(status_code(&first), 500);
+        // Neither delivery was recorded, so the next one is processed
+        let result = handler.handle_webhook(Some(&signature), body).await;
+        assert_eq!(result.unwrap(), Delivery::Dispatched);
+    }
+
+    #[test]
+    fn test_dedupe_capacity() {
+        let mut seen = SeenDeliveries {
+            order: VecDeque::new(),
+            ids: HashSet::new(),
+            capacity: 2,
+            in_flight: HashSet::new(),
+        };
+
+        assert!(seen.insert("a"));
+        assert!(seen.insert("b"));
+        assert!(!seen.insert("a"));
+        assert!(seen.insert("c"));
+        assert!(seen.insert("a"));
+    }
+}